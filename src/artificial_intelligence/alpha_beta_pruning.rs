@@ -1,16 +1,44 @@
-use std::{collections::HashMap};
+use std::{cmp::Reverse, sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}}, thread, time::{Duration, Instant}};
 
-use crate::game::{heuristic::HeuristicWeights, player::Player, state::State};
+use crate::game::{heuristic::{Evaluator, HeuristicWeights}, player::Player, score::{MAX_PLY, MAX_VALUE, MIN_VALUE, is_decisive, win_in}, state::State};
 
-use super::{ArtificialIntelligence, ArtificialIntelligenceResult};
+use super::{ArtificialIntelligence, ArtificialIntelligenceResult, Progress, fallback, Selection, Variation, history::{History, Killers, MoveIndices}, principal_variation::PrincipalVariation, publish, transposition_table::{Bound, Entry, TranspositionTable}};
 
 #[derive(Debug, Clone, Copy)]
 pub struct Counter {
-    visisted: u32,
-    cache_hit: u32,
-    cache_miss: u32,
-    move_ordering_hit: u32,
-    move_ordering_miss: u32,
+    pub visisted: u32,
+    pub cache_hit: u32,
+    pub cache_miss: u32,
+    pub move_ordering_hit: u32,
+    pub move_ordering_miss: u32,
+    pub killer_hit: u32,
+    pub killer_miss: u32,
+    pub history_hit: u32,
+    pub history_miss: u32,
+    pub re_search: u32,
+    /// Iterations searched again after the best value reached the upper bound of the aspiration window
+    pub aspiration_fail_high: u32,
+    /// Iterations searched again after too few values exceeded the lower bound of the aspiration window
+    pub aspiration_fail_low: u32,
+    /// Nodes searched by the quiescence search beyond the limit
+    pub quiescence_visited: u32,
+    /// Moves searched one ply deeper
+    pub extensions: u32,
+    /// Late moves searched to a reduced depth
+    pub reductions: u32,
+    /// Reduced moves searched again to the full depth after beating alpha
+    pub reduction_re_search: u32,
+    /// Moves near the leaves skipped as they cannot raise alpha
+    pub futility_pruned: u32,
+    /// Positions scored as draws as they already occurred on the path from the root
+    pub repetitions: u32,
+    /// Nodes without any value left between a faster win and a slower loss
+    pub mate_distance_pruned: u32,
+    /// Nodes visited by the helper threads
+    pub helper_visisted: u32,
+    pub cache_usage: u32,
+    /// Limit of the last completed iteration
+    pub depth: u8,
 }
 
 impl Counter {
@@ -21,161 +49,636 @@ impl Counter {
             cache_miss: 0,
             move_ordering_hit: 0,
             move_ordering_miss: 0,
+            killer_hit: 0,
+            killer_miss: 0,
+            history_hit: 0,
+            history_miss: 0,
+            re_search: 0,
+            aspiration_fail_high: 0,
+            aspiration_fail_low: 0,
+            quiescence_visited: 0,
+            extensions: 0,
+            reductions: 0,
+            reduction_re_search: 0,
+            futility_pruned: 0,
+            repetitions: 0,
+            mate_distance_pruned: 0,
+            helper_visisted: 0,
+            cache_usage: 0,
+            depth: 0,
         }
     }
 }
 
 #[derive(Debug, Clone)]
-pub struct AlphaBetaPruningOptions {
+pub struct AlphaBetaPruningOptions<E: Evaluator = HeuristicWeights> {
     pub cache: bool,
+    /// Size of the transposition table in megabytes
+    pub cache_size: usize,
     pub limit: u8,
-    pub weights: HeuristicWeights,
+    /// Evaluation of the positions at the limit
+    pub evaluator: E,
     pub move_ordering: bool,
     pub move_ordering_offset: u8,
+    /// Try moves first which caused a cut-off at the same ply, if the cache has no value for them
+    pub killer_moves: bool,
+    /// Try moves first which caused deep cut-offs before, if the cache has no value for them
+    pub history_heuristic: bool,
     pub iterative_deepening: bool,
+    /// Stop iterative deepening after this duration, `limit` still caps the depth
+    pub time_limit: Option<Duration>,
+    /// Stop iterative deepening at this point in time, `limit` still caps the depth
+    pub deadline: Option<Instant>,
+    /// Search all moves after the first with a zero window and re-search them only if they fail high
+    pub principal_variation_search: bool,
+    /// Number of best root moves searched with exact windows and returned as variations
+    pub multi_pv: usize,
+    /// Search every iteration within this distance of the previous value, widened on failures
+    pub aspiration_window: Option<i16>,
+    /// Keep searching mill-closing moves beyond the limit until the position is quiet
+    pub quiescence: bool,
+    /// Also search moves blocking an uncompleted mill of the opponent during quiescence search
+    pub quiescence_blocking: bool,
+    /// Maximal number of nodes of the quiescence search at each leaf, not a limit of the whole search
+    pub quiescence_leaf_nodes: u32,
+    /// Search one ply deeper after a move, which leaves the opponent able to close a mill
+    pub mill_extension: bool,
+    /// Search one ply deeper after a move, which opens a double mill (Zwickmühle)
+    pub double_mill_extension: bool,
+    /// Maximal number of extensions along a single line
+    pub extension_limit: u8,
+    /// Search quiet moves after the first `late_move_index` ones to a reduced depth, if their heuristic is not above alpha
+    pub late_move_reductions: bool,
+    pub late_move_index: usize,
+    /// Skip quiet moves near the leaves, if their heuristic cannot reach alpha
+    pub futility_pruning: bool,
+    /// Maximal gain of the heuristic per ply expected from quiet moves, used by late move reductions and futility pruning
+    pub heuristic_margin: i16,
+    /// Score positions repeating one on the path from the root as draws
+    pub repetitions: bool,
+    /// Narrow the window to the values still reachable by winning or losing from the current ply
+    pub mate_distance_pruning: bool,
+    /// Number of threads searching the root at once with a shared cache (Lazy SMP)
+    pub threads: usize,
+    /// How to choose among equally good moves
+    pub selection: Selection,
 }
 
-impl Default for AlphaBetaPruningOptions {
+impl<E: Evaluator + Default> Default for AlphaBetaPruningOptions<E> {
     fn default() -> Self {
         AlphaBetaPruningOptions {
             cache: true,
+            cache_size: 16,
             limit: 3,
-            weights: Default::default(),
+            evaluator: Default::default(),
             move_ordering: true,
             move_ordering_offset: 1,
+            killer_moves: true,
+            history_heuristic: true,
             iterative_deepening: true,
+            time_limit: None,
+            deadline: None,
+            principal_variation_search: false,
+            multi_pv: 1,
+            aspiration_window: None,
+            quiescence: false,
+            quiescence_blocking: false,
+            quiescence_leaf_nodes: 100,
+            mill_extension: false,
+            double_mill_extension: false,
+            extension_limit: 4,
+            late_move_reductions: false,
+            late_move_index: 3,
+            futility_pruning: false,
+            heuristic_margin: 3,
+            repetitions: true,
+            mate_distance_pruning: true,
+            threads: 1,
+            selection: Selection::First,
         }
     }
 }
 
-pub struct AlphaBetaPruning {
-    cache: HashMap<(State, Player, u8), (i16, i16, i16)>,
+pub struct AlphaBetaPruning<E: Evaluator = HeuristicWeights> {
+    cache: Arc<TranspositionTable>,
     counter: Counter,
-    options: AlphaBetaPruningOptions,
+    options: AlphaBetaPruningOptions<E>,
+    pv: PrincipalVariation,
+    killers: Killers,
+    history: History,
+    stop: Option<Arc<AtomicBool>>,
+    progress: Option<Arc<Mutex<Option<Progress>>>>,
+    deadline: Option<Instant>,
+    abortable: bool,
+    aborted: bool,
+    /// Nodes left for the quiescence search below the current leaf
+    quiescence_budget: u32,
+    /// Extensions along the line to the current node
+    extensions: u8,
+    /// Keys of the positions from the root to the current node
+    path: Vec<u64>,
+    /// Lowest ply of a position on the path, which got repeated in the subtree of the current node.
+    /// Values depending on it are only valid for this path and must not be cached.
+    repetition: Option<u8>,
 }
 
+/// Number of visited nodes between two checks of the deadline and the stop flag
+const ABORT_INTERVAL: u32 = 256;
+/// Futility pruning only applies to nodes with at most this limit
+const FUTILITY_LIMIT: u8 = 2;
 enum Cache {
     Hit(i16),
-    Miss(i16, i16),
+    Miss,
 }
 
-impl AlphaBetaPruning {
-    pub fn new(options: AlphaBetaPruningOptions) -> Self {
+/// Bound of a value found within the window `(alpha, beta)`
+fn bound(value: i16, alpha: i16, beta: i16) -> Bound {
+    if value <= alpha {
+        Bound::Upper
+    } else if value >= beta {
+        Bound::Lower
+    } else {
+        Bound::Exact
+    }
+}
+
+impl<E: Evaluator> AlphaBetaPruning<E> {
+    pub fn new(options: AlphaBetaPruningOptions<E>) -> Self {
         AlphaBetaPruning {
-            cache: HashMap::new(),
+            cache: Arc::new(TranspositionTable::new(if options.cache { options.cache_size } else { 0 })),
             counter: Counter::new(),
-            options: options,
+            options,
+            pv: Default::default(),
+            killers: Default::default(),
+            history: Default::default(),
+            stop: None,
+            progress: None,
+            deadline: None,
+            abortable: false,
+            aborted: false,
+            quiescence_budget: 0,
+            extensions: 0,
+            path: Vec::new(),
+            repetition: None,
+        }
+    }
+
+    /// Search of a helper thread sharing the cache and the deadline, which stops with the flag
+    fn helper(&self, stop: Arc<AtomicBool>) -> Self {
+        AlphaBetaPruning {
+            cache: self.cache.clone(),
+            stop: Some(stop),
+            deadline: self.deadline,
+            // Helpers do not have to return a move
+            abortable: true,
+            // Without allocating a cache of its own
+            ..AlphaBetaPruning::new(AlphaBetaPruningOptions { cache_size: 0, ..self.options.clone() })
         }
     }
 
-    fn get_cache(&self, state: &State, player: Player, alpha: i16, beta: i16, limit: u8) -> Cache {
+    /// Check whether the current iteration has to be aborted.
+    /// Values returned after an abort are meaningless and must not be cached.
+    fn aborted(&mut self) -> bool {
+        if !self.aborted && self.abortable && self.counter.visisted.is_multiple_of(ABORT_INTERVAL) {
+            self.aborted = self.should_stop();
+        }
+        self.aborted
+    }
+
+    fn should_stop(&self) -> bool {
+        self.stop.as_ref().is_some_and(|stop| stop.load(Ordering::Relaxed))
+            || self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    fn get_cache(&self, state: &State, player: Player, alpha: i16, beta: i16, limit: u8, ply: u8) -> Cache {
         if !self.options.cache {
-            return Cache::Miss(alpha, beta)
+            return Cache::Miss
         }
 
-        if let Some((value, cache_alpha, cache_beta)) = self.cache.get(&(state.clone(), player, limit)) {
-            if cache_alpha <= &alpha && &beta <= cache_beta {
-                Cache::Hit(*value)
-            } else {
-                Cache::Miss(alpha.min(*cache_alpha), beta.max(*cache_beta))
-            }
-        } else {
-            Cache::Miss(alpha, beta)
+        match self.cache.get(state, player, ply) {
+            Some(entry) if entry.depth >= limit => match entry.bound {
+                Bound::Exact => Cache::Hit(entry.value),
+                Bound::Lower if entry.value >= beta => Cache::Hit(entry.value),
+                Bound::Upper if entry.value <= alpha => Cache::Hit(entry.value),
+                _ => Cache::Miss,
+            },
+            _ => Cache::Miss,
         }
     }
 
-    fn set_cache(&mut self, state: &State, player: Player, limit: u8, value: i16, alpha: i16, beta: i16) {
-        if self.options.cache {
-            self.cache.insert((state.clone(), player, limit), (value, alpha, beta));
+    fn set_cache(&mut self, state: &State, player: Player, limit: u8, ply: u8, value: i16, bound: Bound) {
+        // The path ends with the current position, whose own repetitions do not depend on the path to it
+        let path_dependent = self.repetition.is_some_and(|repetition| repetition < ply);
+        if self.options.cache && !path_dependent {
+            self.cache.set(state, player, ply, Entry { value, depth: limit, bound });
         }
     }
 
-    fn order_moves(&mut self, mut next_states: Vec<State>, player: Player, limit: u8) -> Vec<State> {
-        if self.options.move_ordering && limit >= self.options.move_ordering_offset {
-            next_states.sort_by_cached_key(|state| {
-                if let Some(cache) = self.cache.get(&(state.clone(), player.opponent(), limit-self.options.move_ordering_offset)) {
+    /// Order by cached values, successor states without one are ordered by killer moves and history
+    fn order_moves(&mut self, state: &State, mut next_states: Vec<State>, player: Player, limit: u8, ply: u8) -> Vec<State> {
+        let cache = self.options.move_ordering && limit >= self.options.move_ordering_offset;
+        if !cache && !self.options.killer_moves && !self.options.history_heuristic {
+            return next_states;
+        }
+
+        next_states.sort_by_cached_key(|next_state| {
+            if cache {
+                let entry = self.cache.get(next_state, player.opponent(), ply + 1)
+                    .filter(|entry| entry.depth >= limit - self.options.move_ordering_offset);
+                if let Some(entry) = entry {
                     self.counter.move_ordering_hit += 1;
-                    cache.0
+                    return (entry.value, 0, Reverse(u32::MAX));
+                }
+                self.counter.move_ordering_miss += 1;
+            }
+
+            let indices = MoveIndices::new(state, next_state, player);
+            let mut killer = 2;
+            if self.options.killer_moves {
+                match self.killers.rank(ply, indices) {
+                    Some(rank) => {
+                        self.counter.killer_hit += 1;
+                        killer = rank;
+                    },
+                    None => self.counter.killer_miss += 1,
+                }
+            }
+            let mut score = 0;
+            if self.options.history_heuristic {
+                score = self.history.score(player, indices);
+                if score > 0 {
+                    self.counter.history_hit += 1;
                 } else {
-                    self.counter.move_ordering_miss += 1;
-                    0
+                    self.counter.history_miss += 1;
                 }
+            }
+            (0, killer, Reverse(score))
+        });
+        next_states
+    }
+
+    /// Remember the move causing a cut-off for ordering its siblings
+    fn add_cut_off(&mut self, state: &State, next_state: &State, player: Player, limit: u8, ply: u8) {
+        if self.options.killer_moves || self.options.history_heuristic {
+            let indices = MoveIndices::new(state, next_state, player);
+            if self.options.killer_moves {
+                self.killers.add(ply, indices);
+            }
+            if self.options.history_heuristic {
+                self.history.add(player, indices, limit);
+            }
+        }
+    }
+
+    /// One more ply for a move leaving a mill to close or opening a double mill, while the line has extensions left
+    fn extension(&mut self, state: &State, next_state: &State, player: Player) -> u8 {
+        if self.extensions >= self.options.extension_limit {
+            return 0;
+        }
+        let extend = (self.options.mill_extension && next_state.can_close_mill(player.opponent()))
+            || (self.options.double_mill_extension && next_state.has_double_mill(player) && !state.has_double_mill(player));
+        if extend {
+            self.counter.extensions += 1;
+            1
+        } else {
+            0
+        }
+    }
+
+    /// Search an iteration within a window around the previous value.
+    /// The window widens until the best value and the best `multi_pv` values are inside of it.
+    fn search_aspiration(&mut self, next_states: &[State], order: &[usize], player: Player, limit: u8, previous: Option<i16>) -> Vec<Variation> {
+        let (mut lower, mut upper) = (MIN_VALUE, MAX_VALUE);
+        let mut delta = self.options.aspiration_window.unwrap_or(0).max(1);
+        if let (Some(_), Some(previous)) = (self.options.aspiration_window, previous) {
+            lower = previous.saturating_sub(delta).max(MIN_VALUE);
+            upper = previous.saturating_add(delta).min(MAX_VALUE);
+        }
+
+        loop {
+            let variations = self.search_root(next_states, order, player, limit, lower, upper);
+            if self.aborted {
+                return variations;
+            }
+            let best = variations.iter()
+                .map(|variation| variation.value())
+                .max().expect("Unable to find maximum");
+            let exact = variations.iter()
+                .filter(|variation| variation.value() > lower)
+                .count();
+
+            delta = delta.saturating_mul(2);
+            if best >= upper && upper < MAX_VALUE {
+                self.counter.aspiration_fail_high += 1;
+                upper = best.saturating_add(delta).min(MAX_VALUE);
+            } else if exact < self.options.multi_pv.clamp(1, variations.len()) && lower > MIN_VALUE {
+                self.counter.aspiration_fail_low += 1;
+                lower = lower.saturating_sub(delta).max(MIN_VALUE);
+            } else {
+                return variations;
+            }
+        }
+    }
+
+    /// Search all root moves in the given order within the window `(lower, upper)`.
+    /// Only the best `multi_pv` moves (and their ties) get exact values, the others are upper bounds.
+    fn search_root(&mut self, next_states: &[State], order: &[usize], player: Player, limit: u8, lower: i16, upper: i16) -> Vec<Variation> {
+        let multi_pv = self.options.multi_pv.max(1);
+        let mut variations = vec![None; next_states.len()];
+        // Exact values found so far in descending order
+        let mut best: Vec<i16> = Vec::with_capacity(multi_pv.min(next_states.len()) + 1);
+        for &index in order {
+            let alpha = if best.len() < multi_pv { lower } else { (best[multi_pv - 1] - 1).max(lower) };
+            let value = -self.value(&next_states[index], player.opponent(), -upper, -alpha, limit, 1);
+            if best.len() < multi_pv || value > alpha {
+                let position = best.partition_point(|other| *other >= value);
+                best.insert(position, value);
+                best.truncate(multi_pv);
+            }
+            variations[index] = Some(Variation::new(value, &next_states[index], self.pv.get(1)));
+        }
+        variations.into_iter().flatten().collect()
+    }
+
+    /// Search only moves closing a mill (and blocking one) until none are left.
+    /// The heuristic serves as lower bound, as the player does not have to make such a move.
+    fn quiescence(&mut self, state: &State, player: Player, mut alpha: i16, beta: i16, ply: u8) -> i16 {
+        self.pv.clear(ply);
+        if state.finished(player) {
+            return state.utility(player, ply);
+        }
+
+        let mut value = self.options.evaluator.evaluate(state, player);
+        if value >= beta || self.quiescence_budget == 0 || ply >= MAX_PLY {
+            return value;
+        }
+        alpha = alpha.max(value);
+
+        let opponent = player.opponent();
+        let stones = state.count_stones(opponent);
+        let uncompleted_mills = state.get_uncompleted_mills(opponent).len();
+        let blocking = self.options.quiescence_blocking;
+        let next_states = state.next_states(player).into_iter()
+            .filter(|next_state| {
+                next_state.count_stones(opponent) < stones
+                    || (blocking && next_state.get_uncompleted_mills(opponent).len() < uncompleted_mills)
             });
+        for next_state in next_states {
+            if self.quiescence_budget == 0 {
+                break;
+            }
+            self.quiescence_budget -= 1;
+            self.counter.quiescence_visited += 1;
+
+            value = value.max(-self.quiescence(&next_state, opponent, -beta, -alpha, ply + 1));
+            if value >= beta {
+                return value;
+            }
+            if value > alpha {
+                alpha = value;
+                self.pv.update(ply, &next_state);
+            }
         }
-        next_states
+        value
     }
 
-    fn value(&mut self, state: &State, player: Player, mut alpha: i16, mut beta: i16, limit: u8) -> i16 {
+    /// Score repetitions of positions on the path as draws, search all others
+    fn value(&mut self, state: &State, player: Player, alpha: i16, beta: i16, limit: u8, ply: u8) -> i16 {
+        if !self.options.repetitions {
+            return self.negamax(state, player, alpha, beta, limit, ply);
+        }
+
+        let key = TranspositionTable::key(state, player);
+        if let Some(position) = self.path.iter().position(|other| *other == key) {
+            self.counter.repetitions += 1;
+            self.pv.clear(ply);
+            let position = position as u8;
+            self.repetition = Some(self.repetition.map_or(position, |repetition| repetition.min(position)));
+            return 0;
+        }
+
+        self.path.push(key);
+        let outer = self.repetition.take();
+        let value = self.negamax(state, player, alpha, beta, limit, ply);
+        self.path.pop();
+        // Repetitions of this position are resolved, the parent depends on the lower ones only
+        let inner = self.repetition.filter(|repetition| *repetition < ply);
+        self.repetition = outer.into_iter().chain(inner).min();
+        value
+    }
+
+    fn negamax(&mut self, state: &State, player: Player, mut alpha: i16, mut beta: i16, limit: u8, ply: u8) -> i16 {
         self.counter.visisted += 1;
+        self.pv.clear(ply);
+        if self.aborted() {
+            return 0;
+        }
 
-        match self.get_cache(state, player, alpha, beta, limit) {
+        if self.options.mate_distance_pruning {
+            // Losing right now is the worst, winning with the next move the best possible value
+            alpha = alpha.max(-win_in(ply));
+            beta = beta.min(win_in(ply + 1));
+            if alpha >= beta {
+                self.counter.mate_distance_pruned += 1;
+                return alpha;
+            }
+        }
+
+        match self.get_cache(state, player, alpha, beta, limit, ply) {
             Cache::Hit(value) => {
                 self.counter.cache_hit += 1;
                 return value
             },
-            Cache::Miss(new_alpha, new_beta) => {
+            Cache::Miss => {
                 self.counter.cache_miss += 1;
-                alpha = new_alpha;
-                beta = new_beta;
             }
         }
         
         if state.finished(player) {
-            return state.utility(player);
+            return state.utility(player, ply);
+        }
+        if ply >= MAX_PLY {
+            return self.options.evaluator.evaluate(state, player);
         }
         if limit == 0 {
-            return state.heuristic(player, &self.options.weights);
+            if self.options.quiescence {
+                self.quiescence_budget = self.options.quiescence_leaf_nodes;
+                return self.quiescence(state, player, alpha, beta, ply);
+            }
+            return self.options.evaluator.evaluate(state, player);
         }
 
+        let alpha_original = alpha;
         let mut value = alpha;
-        for next_state in self.order_moves(state.next_states(player), player, limit) {
-            value = value.max(
-                -self.value(&next_state, player.opponent(), -beta, -alpha, limit - 1)
-            );
+        let margin = self.options.heuristic_margin;
+        let stones = state.count_stones(player.opponent());
+        for (index, next_state) in self.order_moves(state, state.next_states(player), player, limit, ply).into_iter().enumerate() {
+            let extension = self.extension(state, &next_state, player);
+            let next_limit = limit - 1 + extension;
+
+            // Quiet moves neither pound a stone nor get extended
+            let quiet = extension == 0 && next_state.count_stones(player.opponent()) == stones;
+            let estimate = (quiet && (self.options.futility_pruning || self.options.late_move_reductions))
+                .then(|| self.options.evaluator.evaluate(&next_state, player));
+            let futile = self.options.futility_pruning && index > 0 && limit <= FUTILITY_LIMIT && !is_decisive(alpha)
+                && estimate.is_some_and(|estimate| estimate + margin * limit as i16 <= alpha);
+            if futile && !next_state.finished(player.opponent()) {
+                self.counter.futility_pruned += 1;
+                continue;
+            }
+            let reduce = self.options.late_move_reductions && index >= self.options.late_move_index && next_limit >= 2 && !is_decisive(alpha)
+                && estimate.is_some_and(|estimate| estimate + margin <= alpha);
+
+            self.extensions += extension;
+            let mut reduced = None;
+            if reduce {
+                self.counter.reductions += 1;
+                let next_value = -self.value(&next_state, player.opponent(), -alpha - 1, -alpha, next_limit - 1, ply + 1);
+                if next_value > alpha && !self.aborted {
+                    self.counter.reduction_re_search += 1;
+                } else {
+                    reduced = Some(next_value);
+                }
+            }
+            let next_value = match reduced {
+                Some(next_value) => next_value,
+                None if self.options.principal_variation_search && index > 0 => {
+                    // Prove that the move is worse than the first one with a zero window
+                    let scout = -self.value(&next_state, player.opponent(), -alpha - 1, -alpha, next_limit, ply + 1);
+                    if scout > alpha && scout < beta && !self.aborted {
+                        self.counter.re_search += 1;
+                        -self.value(&next_state, player.opponent(), -beta, -alpha, next_limit, ply + 1)
+                    } else {
+                        scout
+                    }
+                },
+                None => -self.value(&next_state, player.opponent(), -beta, -alpha, next_limit, ply + 1),
+            };
+            self.extensions -= extension;
+            value = value.max(next_value);
+            if self.aborted {
+                return 0;
+            }
 
             if value >= beta {
-                self.set_cache(state, player, limit, value, alpha, beta);
+                self.add_cut_off(state, &next_state, player, limit, ply);
+                self.set_cache(state, player, limit, ply, value, bound(value, alpha_original, beta));
                 return value;
             }
-            alpha = value.max(alpha);
+            if value > alpha {
+                alpha = value;
+                self.pv.update(ply, &next_state);
+            }
         }
 
-        self.set_cache(state, player, limit, value, alpha, beta);
+        self.set_cache(state, player, limit, ply, value, bound(value, alpha_original, beta));
         value
     }
+
+    /// Iterative deepening of all root moves.
+    /// Helper threads rotate the root moves and every other one skips the first iteration, so they diverge from the main thread.
+    fn deepen(&mut self, state: &State, player: Player, thread: usize) -> Option<Vec<Variation>> {
+        let offset = (thread % 2) as u8;
+        let start = if self.options.iterative_deepening { offset.min(self.options.limit) } else { self.options.limit };
+
+        let next_states = state.next_states(player);
+        self.path = vec![TranspositionTable::key(state, player)];
+        self.repetition = None;
+
+        // Search the best moves of the previous iteration first to narrow the windows early
+        let mut order: Vec<usize> = (0..next_states.len()).collect();
+        if !order.is_empty() {
+            order.rotate_left(thread % next_states.len());
+        }
+        let mut values: Option<Vec<Variation>> = None;
+        for limit in start..=self.options.limit {
+            let previous = values.as_ref()
+                .and_then(|values| values.iter().map(|variation| variation.value()).max());
+            let iteration = self.search_aspiration(&next_states, &order, player, limit, previous);
+            if self.aborted {
+                break;
+            }
+            order.sort_by_key(|index| Reverse(iteration[*index].value()));
+            publish(&self.progress, limit, &iteration);
+            values = Some(iteration);
+            self.counter.depth = limit;
+
+            self.abortable = true;
+            if self.should_stop() {
+                break;
+            }
+        }
+        values
+    }
 }
 
-impl ArtificialIntelligence for AlphaBetaPruning {
+impl<E: Evaluator> ArtificialIntelligence for AlphaBetaPruning<E> {
     type Counter = Counter;
 
     fn best_moves(&mut self, state: State, player: Player) -> ArtificialIntelligenceResult<Counter> {
         self.counter = Counter::new();
+        self.cache.new_search();
+        self.killers.clear();
+        self.history.age();
 
-        let start = if self.options.iterative_deepening { 0 } else { self.options.limit };
+        self.aborted = false;
+        // With iterative deepening the first iteration always completes to have a move to return
+        self.abortable = !self.options.iterative_deepening;
+        self.deadline = self.options.time_limit
+            .map(|time_limit| Instant::now() + time_limit)
+            .into_iter()
+            .chain(self.options.deadline)
+            .min();
 
-        let next_states = state.next_states(player);
+        let threads = self.options.threads.max(1);
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut helpers: Vec<Self> = (1..threads).map(|_| self.helper(stop.clone())).collect();
 
-        let values = (start..=self.options.limit).into_iter()
-            .map(|limit| -> Vec<(i16, &State)> {
-                next_states.iter()
-                    .map(|s| (self.value(s, player, -100, 100, limit), s))
-                    .collect()
-            })
-            .last()
-            .expect("No value after iterative deepening");
-         
-        let max = values.iter()
-            .map(|value| value.0)
-            .max().expect("Unable to find maximum");
-        let states = values.into_iter()
-            .filter(|value| value.0 == max)
-            .map(|value| value.1.clone())
-            .collect();
-
-        ArtificialIntelligenceResult {
-            states: states,
-            value: max,
-            counter: self.counter.clone(),
+        let (values, helper_values) = thread::scope(|scope| {
+            let state = &state;
+            let handles: Vec<_> = helpers.iter_mut().enumerate()
+                .map(|(index, helper)| scope.spawn(move || helper.deepen(state, player, index + 1)))
+                .collect();
+            let values = self.deepen(state, player, 0);
+            stop.store(true, Ordering::Relaxed);
+            let helper_values: Vec<_> = handles.into_iter()
+                .map(|handle| handle.join().expect("Helper thread panicked"))
+                .collect();
+            (values, helper_values)
+        });
+        let mut values = values;
+
+        // Take the result of the deepest completed search
+        for (helper, helper_values) in helpers.iter().zip(helper_values) {
+            self.counter.helper_visisted += helper.counter.visisted;
+            if let Some(helper_values) = helper_values {
+                if values.is_none() || helper.counter.depth > self.counter.depth {
+                    self.counter.depth = helper.counter.depth;
+                    values = Some(helper_values);
+                }
+            }
         }
+        // Without iterative deepening an aborted search has no values
+        let values = values.unwrap_or_else(|| {
+            let ordered = self.order_moves(&state, state.next_states(player), player, self.options.limit, 0);
+            fallback(&ordered, |next_state| if next_state.finished(player.opponent()) {
+                -next_state.utility(player.opponent(), 1)
+            } else {
+                -self.options.evaluator.evaluate(next_state, player.opponent())
+            })
+        });
+
+        self.counter.cache_usage = self.cache.usage();
+
+        ArtificialIntelligenceResult::new(&values, self.options.multi_pv, self.counter)
+            .select(self.options.selection, &state, player)
+    }
+
+    fn set_stop(&mut self, stop: Option<Arc<AtomicBool>>) {
+        self.stop = stop;
+    }
+
+    fn set_progress(&mut self, progress: Option<Arc<Mutex<Option<Progress>>>>) {
+        self.progress = progress;
     }
 }
@@ -0,0 +1,65 @@
+use std::sync::{Arc, Mutex, atomic::AtomicBool};
+
+use crate::game::{heuristic::HeuristicWeights, player::Player, state::State};
+
+use super::{ArtificialIntelligence, ArtificialIntelligenceResult, Progress, Selection, Variation};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Counter {
+    pub visisted: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GreedyOptions {
+    pub weights: HeuristicWeights,
+    /// How to choose among equally good moves
+    pub selection: Selection,
+}
+
+/// Play the move with the best heuristic one ply ahead.
+///
+/// Winning moves come first, then moves closing a mill, both ordered by the heuristic.
+pub struct Greedy {
+    options: GreedyOptions,
+}
+
+impl Greedy {
+    pub fn new(options: GreedyOptions) -> Self {
+        Greedy { options }
+    }
+}
+
+impl ArtificialIntelligence for Greedy {
+    type Counter = Counter;
+
+    fn best_moves(&mut self, state: State, player: Player) -> ArtificialIntelligenceResult<Counter> {
+        let mut counter = Counter { visisted: 0 };
+        let stones = state.count_stones(player.opponent());
+        // Ordered by whether a move wins, then by whether it closes a mill, then by its value
+        let ranked: Vec<((bool, bool, i16), Variation)> = state.next_states(player).iter()
+            .map(|next_state| {
+                counter.visisted += 1;
+                let finished = next_state.finished(player.opponent());
+                let value = if finished {
+                    -next_state.utility(player.opponent(), 1)
+                } else {
+                    next_state.heuristic(player, &self.options.weights)
+                };
+                let closes_mill = next_state.count_stones(player.opponent()) < stones;
+                ((finished && value > 0, closes_mill, value), Variation::new(value, next_state, &[]))
+            })
+            .collect();
+        let best = ranked.iter().map(|(rank, _)| *rank).max();
+        let variations: Vec<Variation> = ranked.into_iter()
+            .filter(|(rank, _)| Some(*rank) == best)
+            .map(|(_, variation)| variation)
+            .collect();
+        ArtificialIntelligenceResult::new(&variations, 1, counter)
+            .select(self.options.selection, &state, player)
+    }
+
+    /// Choosing a move is instant, there is nothing to stop
+    fn set_stop(&mut self, _stop: Option<Arc<AtomicBool>>) {}
+
+    fn set_progress(&mut self, _progress: Option<Arc<Mutex<Option<Progress>>>>) {}
+}
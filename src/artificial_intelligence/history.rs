@@ -0,0 +1,93 @@
+use crate::game::{masks::{mask_board, offset_board}, player::Player, state::State};
+
+/// Points of a move: from (24 while placing), to and the lowest pounded point (24 without)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoveIndices {
+    from: usize,
+    to: usize,
+    pounded: usize,
+}
+
+impl MoveIndices {
+    /// Read the move from the board bits changed between state and next
+    pub fn new(state: &State, next: &State, player: Player) -> Self {
+        let before: u64 = state.into();
+        let after: u64 = next.into();
+        let changed = before ^ after;
+        let point = |bits: u64, player: Player| -> usize {
+            match (bits & mask_board(player)) >> offset_board(player) {
+                0 => 24,
+                bits => bits.trailing_zeros() as usize,
+            }
+        };
+        MoveIndices {
+            from: point(changed & before, player),
+            to: point(changed & after, player),
+            pounded: point(changed, player.opponent()),
+        }
+    }
+}
+
+/// Two moves per ply, which recently caused a cut-off
+#[derive(Default)]
+pub struct Killers(Vec<[Option<MoveIndices>; 2]>);
+
+impl Killers {
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    /// Position of the move among the killers of ply
+    pub fn rank(&self, ply: u8, indices: MoveIndices) -> Option<usize> {
+        self.0.get(ply as usize)?.iter()
+            .position(|killer| *killer == Some(indices))
+    }
+
+    pub fn add(&mut self, ply: u8, indices: MoveIndices) {
+        let ply = ply as usize;
+        if self.0.len() <= ply {
+            self.0.resize(ply + 1, [None; 2]);
+        }
+        let killers = &mut self.0[ply];
+        if killers[0] != Some(indices) {
+            killers[1] = killers[0];
+            killers[0] = Some(indices);
+        }
+    }
+}
+
+/// Scores of moves by their points, which grow with every cut-off of the move
+#[derive(Default)]
+pub struct History {
+    moves: [[[u32; 24]; 25]; 2],
+    pounded: [[u32; 24]; 2],
+}
+
+impl History {
+    /// Halve all scores, so older searches count less
+    pub fn age(&mut self) {
+        self.moves.iter_mut().flatten().flatten().for_each(|score| *score /= 2);
+        self.pounded.iter_mut().flatten().for_each(|score| *score /= 2);
+    }
+
+    pub fn score(&self, player: Player, indices: MoveIndices) -> u32 {
+        let player = player as usize;
+        let pounded = match indices.pounded {
+            24 => 0,
+            pounded => self.pounded[player][pounded],
+        };
+        self.moves[player][indices.from][indices.to].saturating_add(pounded)
+    }
+
+    /// Deeper cut-offs weigh more
+    pub fn add(&mut self, player: Player, indices: MoveIndices, limit: u8) {
+        let player = player as usize;
+        let bonus = limit as u32 * limit as u32;
+        let score = &mut self.moves[player][indices.from][indices.to];
+        *score = score.saturating_add(bonus);
+        if indices.pounded != 24 {
+            let score = &mut self.pounded[player][indices.pounded];
+            *score = score.saturating_add(bonus);
+        }
+    }
+}
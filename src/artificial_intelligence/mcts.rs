@@ -0,0 +1,315 @@
+use std::{f64::consts::SQRT_2, sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}}, time::{Duration, Instant}};
+
+use crate::game::{heuristic::HeuristicWeights, player::Player, state::State};
+
+use super::{ArtificialIntelligence, ArtificialIntelligenceResult, Progress, Selection, Variation, publish, rng::Rng};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Counter {
+    pub iterations: u32,
+    /// Nodes of the tree after the search
+    pub nodes: u32,
+    /// Nodes kept from the previous search
+    pub reused: u32,
+    pub rollout_plies: u32,
+}
+
+impl Counter {
+    fn new() -> Counter {
+        Counter {
+            iterations: 0,
+            nodes: 0,
+            reused: 0,
+            rollout_plies: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rollout {
+    /// Play uniformly random moves
+    Random,
+    /// Play the better one of two random moves by the heuristic
+    Heuristic,
+}
+
+#[derive(Debug, Clone)]
+pub struct MctsOptions {
+    /// Maximal number of iterations per search
+    pub iterations: u32,
+    /// Stop iterating after this duration, `iterations` still caps the search
+    pub time_limit: Option<Duration>,
+    /// Weight of the exploration term of UCT
+    pub exploration: f64,
+    pub rollout: Rollout,
+    /// Rollouts still running after this many plies count as draws
+    pub rollout_limit: u32,
+    /// Only used by heuristic rollouts
+    pub weights: HeuristicWeights,
+    /// Keep the subtree of the searched position from the previous search
+    pub reuse_tree: bool,
+    pub seed: u64,
+    /// How to choose among equally good moves
+    pub selection: Selection,
+}
+
+impl Default for MctsOptions {
+    fn default() -> Self {
+        MctsOptions {
+            iterations: 10_000,
+            time_limit: None,
+            exploration: SQRT_2,
+            rollout: Rollout::Random,
+            rollout_limit: 200,
+            weights: Default::default(),
+            reuse_tree: true,
+            seed: 0,
+            selection: Selection::First,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Node {
+    state: State,
+    /// Player to move in state
+    player: Player,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    /// Successor states without a node yet
+    unexpanded: Vec<State>,
+    /// Reward of a finished state for the player who moved into it
+    outcome: Option<f64>,
+    visits: u32,
+    /// Sum of the rewards for the player who moved into state
+    reward: f64,
+}
+
+impl Node {
+    fn new(state: State, player: Player, parent: Option<usize>) -> Self {
+        let (unexpanded, outcome) = if state.finished(player) {
+            (Vec::new(), Some(0.5 - state.utility(player, 0).signum() as f64 / 2.0))
+        } else {
+            (state.next_states(player), None)
+        };
+        Node { state, player, parent, children: Vec::new(), unexpanded, outcome, visits: 0, reward: 0.0 }
+    }
+}
+
+/// Number of iterations between two publications of the progress
+const PROGRESS_INTERVAL: u32 = 1000;
+
+/// Monte Carlo tree search: grow a tree by UCT selection and score its leaves by playing random games.
+///
+/// The most visited move is the best one. Values are win rates scaled to `-100..=100`,
+/// other moves are capped below the most visited one.
+pub struct Mcts {
+    options: MctsOptions,
+    counter: Counter,
+    rng: Rng,
+    /// Tree in an arena, the root is the first node
+    nodes: Vec<Node>,
+    stop: Option<Arc<AtomicBool>>,
+    progress: Option<Arc<Mutex<Option<Progress>>>>,
+}
+
+impl Mcts {
+    pub fn new(options: MctsOptions) -> Self {
+        Mcts {
+            rng: Rng::new(options.seed),
+            options,
+            counter: Counter::new(),
+            nodes: Vec::new(),
+            stop: None,
+            progress: None,
+        }
+    }
+
+    /// Continue with the subtree of state from the previous search if it is among the next two plies
+    fn reuse(&mut self, state: &State, player: Player) -> bool {
+        if !self.options.reuse_tree || self.nodes.is_empty() {
+            return false;
+        }
+        let found = self.nodes[0].children.iter()
+            .flat_map(|child| self.nodes[*child].children.iter().copied().chain([*child]))
+            .find(|index| self.nodes[*index].state == *state && self.nodes[*index].player == player);
+        match found {
+            Some(index) => {
+                self.nodes = self.subtree(index);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Copy the subtree of index into a new arena
+    fn subtree(&self, index: usize) -> Vec<Node> {
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut stack = vec![(index, None)];
+        while let Some((index, parent)) = stack.pop() {
+            let node = &self.nodes[index];
+            let new = nodes.len();
+            nodes.push(Node { parent, children: Vec::new(), ..node.clone() });
+            if let Some(parent) = parent {
+                nodes[parent].children.push(new);
+            }
+            stack.extend(node.children.iter().map(|child| (*child, Some(new))));
+        }
+        nodes
+    }
+
+    /// Child with the highest upper confidence bound
+    fn select(&self, index: usize) -> usize {
+        let node = &self.nodes[index];
+        let log_visits = (node.visits as f64).ln();
+        let uct = |child: &usize| {
+            let child = &self.nodes[*child];
+            let visits = child.visits as f64;
+            child.reward / visits + self.options.exploration * (log_visits / visits).sqrt()
+        };
+        *node.children.iter()
+            .max_by(|a, b| uct(a).total_cmp(&uct(b)))
+            .expect("Node has children")
+    }
+
+    /// Play until the end of the game and return the reward for the opponent of player
+    fn rollout(&mut self, mut state: State, mut player: Player) -> f64 {
+        let mover = player.opponent();
+        for _ in 0..self.options.rollout_limit {
+            let next_states = state.next_states(player);
+            if next_states.is_empty() || !state.has_enough_stones(player) {
+                return if player == mover { 0.0 } else { 1.0 };
+            }
+            self.counter.rollout_plies += 1;
+            let mut next = self.rng.below(next_states.len());
+            if self.options.rollout == Rollout::Heuristic {
+                let other = self.rng.below(next_states.len());
+                if next_states[other].heuristic(player, &self.options.weights) > next_states[next].heuristic(player, &self.options.weights) {
+                    next = other;
+                }
+            }
+            state = next_states[next].clone();
+            player = player.opponent();
+        }
+        0.5
+    }
+
+    fn iterate(&mut self) {
+        self.counter.iterations += 1;
+
+        let mut index = 0;
+        while self.nodes[index].unexpanded.is_empty() && !self.nodes[index].children.is_empty() {
+            index = self.select(index);
+        }
+
+        if !self.nodes[index].unexpanded.is_empty() {
+            let position = self.rng.below(self.nodes[index].unexpanded.len());
+            let state = self.nodes[index].unexpanded.swap_remove(position);
+            let child = Node::new(state, self.nodes[index].player.opponent(), Some(index));
+            self.nodes.push(child);
+            let child = self.nodes.len() - 1;
+            self.nodes[index].children.push(child);
+            index = child;
+        }
+
+        let mut reward = match self.nodes[index].outcome {
+            Some(outcome) => outcome,
+            None => self.rollout(self.nodes[index].state.clone(), self.nodes[index].player),
+        };
+        loop {
+            let node = &mut self.nodes[index];
+            node.visits += 1;
+            node.reward += reward;
+            reward = 1.0 - reward;
+            match node.parent {
+                Some(parent) => index = parent,
+                None => break,
+            }
+        }
+    }
+
+    /// Line of the most visited children below index
+    fn principal_variation(&self, mut index: usize) -> Vec<State> {
+        let mut line = Vec::new();
+        while let Some(child) = self.nodes[index].children.iter().max_by_key(|child| self.nodes[**child].visits) {
+            line.push(self.nodes[*child].state.clone());
+            index = *child;
+        }
+        line
+    }
+
+    /// Variations of all successor states ordered like `next_states`
+    fn variations(&self, next_states: &[State]) -> Vec<Variation> {
+        let root = &self.nodes[0];
+        let child = |state: &State| root.children.iter()
+            .find(|child| self.nodes[**child].state == *state)
+            .copied();
+        let most_visited = root.children.iter()
+            .max_by_key(|child| self.nodes[**child].visits)
+            .map_or(0, |child| self.nodes[*child].visits);
+        let rate = |child: usize| {
+            let node = &self.nodes[child];
+            ((node.reward / node.visits as f64 * 2.0 - 1.0) * 100.0).round() as i16
+        };
+        let best = root.children.iter()
+            .filter(|child| self.nodes[**child].visits == most_visited && most_visited > 0)
+            .map(|child| rate(*child))
+            .max();
+
+        next_states.iter()
+            .map(|state| match (child(state), best) {
+                (Some(child), Some(best)) if self.nodes[child].visits > 0 => {
+                    let value = rate(child);
+                    let value = if self.nodes[child].visits == most_visited { value } else { value.min(best - 1) };
+                    Variation::new(value, state, &self.principal_variation(child))
+                },
+                (_, Some(best)) => Variation::new(best - 1, state, &[]),
+                (_, None) => Variation::new(0, state, &[]),
+            })
+            .collect()
+    }
+
+    fn should_stop(&self, deadline: Option<Instant>) -> bool {
+        self.stop.as_ref().is_some_and(|stop| stop.load(Ordering::Relaxed))
+            || deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+impl ArtificialIntelligence for Mcts {
+    type Counter = Counter;
+
+    fn best_moves(&mut self, state: State, player: Player) -> ArtificialIntelligenceResult<Counter> {
+        self.counter = Counter::new();
+        let deadline = self.options.time_limit.map(|time_limit| Instant::now() + time_limit);
+
+        if self.reuse(&state, player) {
+            self.counter.reused = self.nodes.len() as u32;
+        } else {
+            self.nodes = vec![Node::new(state.clone(), player, None)];
+        }
+        let next_states = state.next_states(player);
+
+        for iteration in 1..=self.options.iterations {
+            if self.should_stop(deadline) {
+                break;
+            }
+            self.iterate();
+            if iteration.is_multiple_of(PROGRESS_INTERVAL) {
+                let variations = self.variations(&next_states);
+                publish(&self.progress, self.principal_variation(0).len() as u8, &variations);
+            }
+        }
+        self.counter.nodes = self.nodes.len() as u32;
+
+        ArtificialIntelligenceResult::new(&self.variations(&next_states), 1, self.counter)
+            .select(self.options.selection, &state, player)
+    }
+
+    fn set_stop(&mut self, stop: Option<Arc<AtomicBool>>) {
+        self.stop = stop;
+    }
+
+    fn set_progress(&mut self, progress: Option<Arc<Mutex<Option<Progress>>>>) {
+        self.progress = progress;
+    }
+}
@@ -1,15 +1,17 @@
-use std::collections::HashMap;
+use std::{sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}}, thread};
 
-use crate::game::{heuristic::HeuristicWeights, player::Player, state::State};
+use crate::game::{heuristic::{Evaluator, HeuristicWeights}, player::Player, score::MAX_PLY, state::State};
 
-use super::{ArtificialIntelligence, ArtificialIntelligenceResult};
+use super::{ArtificialIntelligence, ArtificialIntelligenceResult, Progress, Selection, Variation, principal_variation::PrincipalVariation, publish, transposition_table::{Bound, Entry, TranspositionTable}};
 
 
 #[derive(Debug, Clone, Copy)]
 pub struct Counter {
-    visisted: u32,
-    cache_hit: u32,
-    cache_miss: u32,
+    pub visisted: u32,
+    pub cache_hit: u32,
+    pub cache_miss: u32,
+    /// Per mille of the cache in use, summed over the caches of all threads
+    pub cache_usage: u32,
 }
 
 impl Counter {
@@ -18,102 +20,226 @@ impl Counter {
             visisted: 0,
             cache_hit: 0,
             cache_miss: 0,
+            cache_usage: 0,
         }
     }
 }
 
 #[derive(Debug, Clone)]
-pub struct MinimaxOptions {
+pub struct MinimaxOptions<E: Evaluator = HeuristicWeights> {
     pub cache: bool,
+    /// Size of the transposition table in megabytes
+    pub cache_size: usize,
     pub limit: u8,
-    pub weights: HeuristicWeights,
+    /// Evaluation of the positions at the limit
+    pub evaluator: E,
+    /// Number of threads splitting the root moves among each other, each with a cache of its own
+    pub threads: usize,
+    /// How to choose among equally good moves
+    pub selection: Selection,
 }
 
-impl Default for MinimaxOptions {
+impl<E: Evaluator + Default> Default for MinimaxOptions<E> {
     fn default() -> Self {
         MinimaxOptions {
             cache: true,
+            cache_size: 16,
             limit: 3,
-            weights: Default::default(),
+            evaluator: Default::default(),
+            threads: 1,
+            selection: Selection::First,
         }
     }
 }
 
-pub struct Minimax {
-    cache: HashMap<(State, Player, u8), i16>,
+pub struct Minimax<E: Evaluator = HeuristicWeights> {
+    cache: TranspositionTable,
     counter: Counter,
-    options: MinimaxOptions,
+    options: MinimaxOptions<E>,
+    pv: PrincipalVariation,
+    stop: Option<Arc<AtomicBool>>,
+    progress: Option<Arc<Mutex<Option<Progress>>>>,
+    aborted: bool,
 }
 
-impl Minimax {
-    pub fn new(options: MinimaxOptions) -> Self {
+/// Number of visited nodes between two checks of the stop flag
+const ABORT_INTERVAL: u32 = 256;
+
+impl<E: Evaluator> Minimax<E> {
+    pub fn new(options: MinimaxOptions<E>) -> Self {
         Minimax {
-            cache: HashMap::new(),
+            // With several threads only the workers use caches
+            cache: TranspositionTable::new(if options.cache && options.threads <= 1 { options.cache_size } else { 0 }),
             counter: Counter::new(),
-            options: options
+            options,
+            pv: Default::default(),
+            stop: None,
+            progress: None,
+            aborted: false,
+        }
+    }
+
+    /// Check whether the search has to be aborted.
+    /// Values returned after an abort are meaningless and must not be cached.
+    fn aborted(&mut self) -> bool {
+        if !self.aborted && self.counter.visisted.is_multiple_of(ABORT_INTERVAL) {
+            self.aborted = self.stop.as_ref().is_some_and(|stop| stop.load(Ordering::Relaxed));
         }
+        self.aborted
     }
 
-    fn get_cache(&self, state: &State, player: Player, limit: u8) -> Option<i16> {
+    fn get_cache(&self, state: &State, player: Player, limit: u8, ply: u8) -> Option<i16> {
         match self.options.cache {
-            true => self.cache.get(&(state.clone(), player, limit)).map(|value| *value),
+            true => self.cache.get(state, player, ply)
+                .filter(|entry| entry.depth >= limit)
+                .map(|entry| entry.value),
             false => None,
         }
     }
 
-    fn set_cache(&mut self, state: &State, player: Player, limit: u8, value: i16) {
+    fn set_cache(&mut self, state: &State, player: Player, limit: u8, ply: u8, value: i16) {
         if self.options.cache {
-            self.cache.insert((state.clone(), player, limit), value);
+            self.cache.set(state, player, ply, Entry { value, depth: limit, bound: Bound::Exact });
         }
     }
 
-    fn value(&mut self, state: &State, player: Player, limit: u8) -> i16 {
+    fn value(&mut self, state: &State, player: Player, limit: u8, ply: u8) -> i16 {
         self.counter.visisted += 1;
-        
-        if let Some(cache) = self.get_cache(state, player, limit) {
+        self.pv.clear(ply);
+        if self.aborted() {
+            return 0;
+        }
+
+        if let Some(cache) = self.get_cache(state, player, limit, ply) {
             self.counter.cache_hit += 1;
             return cache;
         }
         self.counter.cache_miss += 1;
         
         if state.finished(player) {
-            return state.utility(player);
+            return state.utility(player, ply);
         }
-        if limit == 0 {
-            return state.heuristic(player, &self.options.weights);
+        if limit == 0 || ply >= MAX_PLY {
+            return self.options.evaluator.evaluate(state, player);
         }
 
-        let value = state.next_states(player).iter()
-            .map(|s| -self.value(s, player.opponent(), limit-1))
-            .max()
-            .expect("next_state was empty, but finished was false");
-        self.set_cache(state, player, limit, value);
+        let mut value = None;
+        for next_state in state.next_states(player) {
+            let next_value = -self.value(&next_state, player.opponent(), limit-1, ply+1);
+            if value.is_none_or(|value| next_value > value) {
+                value = Some(next_value);
+                self.pv.update(ply, &next_state);
+            }
+        }
+        let value = value.expect("next_state was empty, but finished was false");
+        if self.aborted {
+            return 0;
+        }
+        self.set_cache(state, player, limit, ply, value);
 
         value
     }
 }
 
-impl ArtificialIntelligence for Minimax {
+impl<E: Evaluator> Minimax<E> {
+    /// Search every `step`-th root move starting with `first`
+    fn search_root(&mut self, next_states: &[State], player: Player, first: usize, step: usize) -> Vec<Variation> {
+        let mut values: Vec<Variation> = Vec::new();
+        for s in next_states.iter().skip(first).step_by(step) {
+            let value = -self.value(s, player.opponent(), self.options.limit, 1);
+            if self.aborted {
+                break;
+            }
+            values.push(Variation::new(value, s, self.pv.get(1)));
+            publish(&self.progress, self.options.limit, &values);
+        }
+        values
+    }
+
+    /// Worker of a parallel search with a cache of its own, it only shares the stop flag
+    fn worker(&self) -> Self {
+        Minimax {
+            stop: self.stop.clone(),
+            ..Minimax::new(MinimaxOptions { threads: 1, ..self.options.clone() })
+        }
+    }
+
+    /// Split the root moves round-robin among threads, so every thread searches the same moves every time
+    fn search_parallel(&mut self, next_states: &[State], player: Player) -> Vec<Variation> {
+        let threads = self.options.threads;
+        let mut workers: Vec<Self> = (0..threads).map(|_| self.worker()).collect();
+
+        let mut values: Vec<_> = thread::scope(|scope| {
+            let handles: Vec<_> = workers.iter_mut().enumerate()
+                .map(|(first, worker)| scope.spawn(move || worker.search_root(next_states, player, first, threads).into_iter()))
+                .collect();
+            handles.into_iter()
+                .map(|handle| handle.join().expect("Worker thread panicked"))
+                .collect()
+        });
+
+        for worker in &workers {
+            self.counter.visisted += worker.counter.visisted;
+            self.counter.cache_hit += worker.counter.cache_hit;
+            self.counter.cache_miss += worker.counter.cache_miss;
+            self.counter.cache_usage += worker.cache.usage();
+            self.aborted |= worker.aborted;
+        }
+
+        // Restore the order of next_states, stopped workers may miss their last moves
+        let mut variations = Vec::with_capacity(next_states.len());
+        for index in 0..next_states.len() {
+            match values[index % threads].next() {
+                Some(variation) => variations.push(variation),
+                None => break,
+            }
+        }
+        publish(&self.progress, self.options.limit, &variations);
+        variations
+    }
+}
+
+impl<E: Evaluator> ArtificialIntelligence for Minimax<E> {
     type Counter = Counter;
 
     fn best_moves(&mut self, state: State, player: Player) -> ArtificialIntelligenceResult<Counter> {
         self.counter = Counter::new();
-
-        let values: Vec<(i16, State)> = state.next_states(player).into_iter()
-            .map(|s| (self.value(&s, player, self.options.limit), s))
-            .collect();
-        let max = values.iter()
-            .map(|value| value.0)
-            .max().expect("Unable to find maximum");
-        let states = values.into_iter()
-            .filter(|value| value.0 == max)
-            .map(|value| value.1)
-            .collect();
-
-        ArtificialIntelligenceResult {
-            states: states,
-            value: max,
-            counter: self.counter.clone(),
+        self.cache.new_search();
+        self.aborted = false;
+
+        let next_states = state.next_states(player);
+
+        let mut values = if self.options.threads > 1 {
+            self.search_parallel(&next_states, player)
+        } else {
+            let values = self.search_root(&next_states, player, 0, 1);
+            self.counter.cache_usage = self.cache.usage();
+            values
+        };
+        if values.is_empty() {
+            // Stopped before the first move was searched, fall back to a static evaluation
+            values = next_states.into_iter()
+                .map(|s| {
+                    let value = if s.finished(player.opponent()) {
+                        s.utility(player.opponent(), 1)
+                    } else {
+                        self.options.evaluator.evaluate(&s, player.opponent())
+                    };
+                    Variation::new(-value, &s, &[])
+                })
+                .collect();
         }
+
+        // Every value is exact, so all moves are variations
+        ArtificialIntelligenceResult::new(&values, values.len(), self.counter)
+            .select(self.options.selection, &state, player)
+    }
+
+    fn set_stop(&mut self, stop: Option<Arc<AtomicBool>>) {
+        self.stop = stop;
+    }
+
+    fn set_progress(&mut self, progress: Option<Arc<Mutex<Option<Progress>>>>) {
+        self.progress = progress;
     }
 }
@@ -1,17 +1,184 @@
+use std::{cmp::Reverse, sync::{Arc, Mutex, atomic::AtomicBool}};
+
 use crate::game::{player::Player, state::State};
 
+use rng::Rng;
+use transposition_table::TranspositionTable;
+
 pub mod minimax;
 pub mod alpha_beta_pruning;
+pub mod mtdf;
+pub mod mcts;
+pub mod proof_number;
+pub mod random;
+pub mod greedy;
+pub mod skill;
+pub mod rng;
+pub mod transposition_table;
+pub mod history;
+pub mod search_handle;
+pub mod ponder;
+pub mod principal_variation;
 
 pub trait ArtificialIntelligence {
     type Counter;
 
     fn best_moves(&mut self, state: State, player: Player) -> ArtificialIntelligenceResult<Self::Counter>;
+
+    /// Stop the search as soon as the flag is set and return the best result found so far
+    fn set_stop(&mut self, stop: Option<Arc<AtomicBool>>);
+
+    /// Publish the best result found so far whenever it changes
+    fn set_progress(&mut self, progress: Option<Arc<Mutex<Option<Progress>>>>);
 }
 
 #[derive(Debug)]
 pub struct ArtificialIntelligenceResult<Counter> {
     states: Vec<State>,
+    /// Index of the state chosen among the best ones
+    chosen: usize,
     value: i16,
+    pv: Vec<State>,
+    variations: Vec<Variation>,
     counter: Counter,
 }
+
+impl<Counter> ArtificialIntelligenceResult<Counter> {
+    /// Collect all best variations, the first one becomes the principal variation.
+    /// The best `multi_pv` variations are kept sorted by value.
+    fn new(variations: &[Variation], multi_pv: usize, counter: Counter) -> Self {
+        let (value, states) = maximum(variations);
+        let mut sorted = variations.to_vec();
+        sorted.sort_by_key(|variation| Reverse(variation.value));
+        sorted.truncate(multi_pv.max(1));
+        let pv = sorted[0].states.clone();
+        ArtificialIntelligenceResult { states, chosen: 0, value, pv, variations: sorted, counter }
+    }
+
+    /// Choose one of the best states by the selection policy, the principal variation starts with it
+    fn select(mut self, selection: Selection, state: &State, player: Player) -> Self {
+        self.chosen = selection.select(state, player, &self.states);
+        let chosen = &self.states[self.chosen];
+        self.pv = self.variations.iter()
+            .find(|variation| variation.states[0] == *chosen)
+            .map_or_else(|| vec![chosen.clone()], |variation| variation.states.clone());
+        self
+    }
+
+    /// All best successor states, ordered like `next_states`
+    pub fn states(&self) -> &[State] {
+        &self.states
+    }
+
+    /// The best successor state to play, chosen by the selection policy of the artificial intelligence
+    pub fn chosen(&self) -> &State {
+        &self.states[self.chosen]
+    }
+
+    pub fn value(&self) -> i16 {
+        self.value
+    }
+
+    /// Expected line of play starting with the chosen successor state
+    pub fn pv(&self) -> &[State] {
+        &self.pv
+    }
+
+    /// Best variations sorted by value, at least the principal variation
+    pub fn variations(&self) -> &[Variation] {
+        &self.variations
+    }
+
+    pub fn counter(&self) -> &Counter {
+        &self.counter
+    }
+}
+
+/// A successor state of the root with its value and the expected line of play starting with it
+#[derive(Debug, Clone)]
+pub struct Variation {
+    value: i16,
+    states: Vec<State>,
+}
+
+impl Variation {
+    fn new(value: i16, state: &State, line: &[State]) -> Self {
+        let mut states = vec![state.clone()];
+        states.extend_from_slice(line);
+        Variation { value, states }
+    }
+
+    pub fn value(&self) -> i16 {
+        self.value
+    }
+
+    /// Expected line of play starting with the successor state of the root
+    pub fn states(&self) -> &[State] {
+        &self.states
+    }
+}
+
+/// How to choose among equally good successor states
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Selection {
+    /// The first one in the order of `next_states`
+    #[default]
+    First,
+    /// A random one, which only depends on the seed and the position, so it stays the same among the same states
+    Random(u64),
+    /// The first one closing a mill, or the first one if none does
+    MillClosing,
+}
+
+impl Selection {
+    /// Index of the chosen state among the best states
+    fn select(self, state: &State, player: Player, states: &[State]) -> usize {
+        match self {
+            Selection::First => 0,
+            Selection::Random(seed) => Rng::new(seed ^ TranspositionTable::key(state, player)).below(states.len()),
+            Selection::MillClosing => {
+                let stones = state.count_stones(player.opponent());
+                states.iter()
+                    .position(|next_state| next_state.count_stones(player.opponent()) < stones)
+                    .unwrap_or(0)
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Progress {
+    pub depth: u8,
+    pub states: Vec<State>,
+    pub value: i16,
+}
+
+/// Find the maximal value and all successor states reaching it
+fn maximum(variations: &[Variation]) -> (i16, Vec<State>) {
+    let max = variations.iter()
+        .map(|variation| variation.value)
+        .max().expect("Unable to find maximum");
+    let states = variations.iter()
+        .filter(|variation| variation.value == max)
+        .map(|variation| variation.states[0].clone())
+        .collect();
+    (max, states)
+}
+
+/// Variations of a search aborted before completing an iteration: the first of the ordered
+/// successor states is the best one, the others get their static values capped below it
+fn fallback(ordered: &[State], value: impl Fn(&State) -> i16) -> Vec<Variation> {
+    let best = ordered.first().map_or(0, &value);
+    ordered.iter()
+        .enumerate()
+        .map(|(index, state)| Variation::new(if index == 0 { best } else { value(state).min(best - 1) }, state, &[]))
+        .collect()
+}
+
+/// Publish a new best result if anyone is listening
+fn publish(progress: &Option<Arc<Mutex<Option<Progress>>>>, depth: u8, variations: &[Variation]) {
+    if let Some(progress) = progress {
+        let (value, states) = maximum(variations);
+        *progress.lock().expect("Progress lock poisoned") = Some(Progress { depth, states, value });
+    }
+}
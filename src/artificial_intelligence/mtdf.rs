@@ -0,0 +1,280 @@
+use std::{sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}}, time::{Duration, Instant}};
+
+use crate::game::{heuristic::HeuristicWeights, player::Player, score::{MAX_PLY, MAX_VALUE, MIN_VALUE}, state::State};
+
+use super::{ArtificialIntelligence, ArtificialIntelligenceResult, Progress, fallback, Selection, Variation, publish, transposition_table::{Bound, Entry, TranspositionTable}};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Counter {
+    pub visisted: u32,
+    pub cache_hit: u32,
+    pub cache_miss: u32,
+    /// Zero window searches of the root
+    pub passes: u32,
+    pub cache_usage: u32,
+    /// Limit of the last completed iteration
+    pub depth: u8,
+}
+
+impl Counter {
+    fn new() -> Counter {
+        Counter {
+            visisted: 0,
+            cache_hit: 0,
+            cache_miss: 0,
+            passes: 0,
+            cache_usage: 0,
+            depth: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MtdfOptions {
+    /// Size of the transposition table in megabytes, MTD(f) does not work without it
+    pub cache_size: usize,
+    pub limit: u8,
+    pub weights: HeuristicWeights,
+    pub move_ordering: bool,
+    pub iterative_deepening: bool,
+    /// Stop iterative deepening after this duration, `limit` still caps the depth
+    pub time_limit: Option<Duration>,
+    /// How to choose among equally good moves
+    pub selection: Selection,
+}
+
+impl Default for MtdfOptions {
+    fn default() -> Self {
+        MtdfOptions {
+            cache_size: 16,
+            limit: 3,
+            weights: Default::default(),
+            move_ordering: true,
+            iterative_deepening: true,
+            time_limit: None,
+            selection: Selection::First,
+        }
+    }
+}
+
+/// Memory-enhanced Test Driver: find the value by repeated zero window searches,
+/// which reuse the bounds stored in the cache by the previous ones.
+pub struct Mtdf {
+    cache: TranspositionTable,
+    counter: Counter,
+    options: MtdfOptions,
+    stop: Option<Arc<AtomicBool>>,
+    progress: Option<Arc<Mutex<Option<Progress>>>>,
+    deadline: Option<Instant>,
+    abortable: bool,
+    aborted: bool,
+}
+
+/// Number of visited nodes between two checks of the deadline and the stop flag
+const ABORT_INTERVAL: u32 = 256;
+
+impl Mtdf {
+    pub fn new(options: MtdfOptions) -> Self {
+        Mtdf {
+            cache: TranspositionTable::new(options.cache_size),
+            counter: Counter::new(),
+            options,
+            stop: None,
+            progress: None,
+            deadline: None,
+            abortable: false,
+            aborted: false,
+        }
+    }
+
+    /// Check whether the current iteration has to be aborted.
+    /// Values returned after an abort are meaningless and must not be cached.
+    fn aborted(&mut self) -> bool {
+        if !self.aborted && self.abortable && self.counter.visisted.is_multiple_of(ABORT_INTERVAL) {
+            self.aborted = self.should_stop();
+        }
+        self.aborted
+    }
+
+    fn should_stop(&self) -> bool {
+        self.stop.as_ref().is_some_and(|stop| stop.load(Ordering::Relaxed))
+            || self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    fn order_moves(&self, mut next_states: Vec<State>, player: Player, ply: u8) -> Vec<State> {
+        if self.options.move_ordering {
+            next_states.sort_by_cached_key(|state| {
+                self.cache.get(state, player.opponent(), ply + 1).map_or(0, |entry| entry.value)
+            });
+        }
+        next_states
+    }
+
+    /// Converge on the value of state by zero window searches, starting at guess
+    fn mtdf(&mut self, state: &State, player: Player, guess: i16, limit: u8) -> i16 {
+        let mut value = guess;
+        let mut lower = MIN_VALUE;
+        let mut upper = MAX_VALUE;
+        while lower < upper {
+            let beta = value.max(lower + 1);
+            self.counter.passes += 1;
+            value = self.value(state, player, beta, limit, 0);
+            if self.aborted {
+                return 0;
+            }
+            if value < beta {
+                upper = value;
+            } else {
+                lower = value;
+            }
+        }
+        value
+    }
+
+    /// Fail-soft alpha-beta pruning with the zero window `(beta - 1, beta)`
+    fn value(&mut self, state: &State, player: Player, beta: i16, limit: u8, ply: u8) -> i16 {
+        self.counter.visisted += 1;
+        if self.aborted() {
+            return 0;
+        }
+
+        if let Some(entry) = self.cache.get(state, player, ply).filter(|entry| entry.depth >= limit) {
+            let hit = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => entry.value >= beta,
+                Bound::Upper => entry.value < beta,
+            };
+            if hit {
+                self.counter.cache_hit += 1;
+                return entry.value;
+            }
+        }
+        self.counter.cache_miss += 1;
+
+        if state.finished(player) {
+            return state.utility(player, ply);
+        }
+        if limit == 0 || ply >= MAX_PLY {
+            return state.heuristic(player, &self.options.weights);
+        }
+
+        let mut value = MIN_VALUE;
+        for next_state in self.order_moves(state.next_states(player), player, ply) {
+            value = value.max(-self.value(&next_state, player.opponent(), 1 - beta, limit - 1, ply + 1));
+            if self.aborted {
+                return 0;
+            }
+            if value >= beta {
+                break;
+            }
+        }
+
+        let bound = if value >= beta { Bound::Lower } else { Bound::Upper };
+        self.cache.set(state, player, ply, Entry { value, depth: limit, bound });
+        value
+    }
+
+    /// Follow the cache from state as long as a successor state explains value
+    fn principal_variation(&self, state: &State, player: Player, value: i16, limit: u8) -> Vec<State> {
+        let mut line = Vec::new();
+        let (mut state, mut player, mut value) = (state.clone(), player, value);
+        for ply in 2..limit.saturating_add(2) {
+            if state.finished(player) {
+                break;
+            }
+            let next = state.next_states(player).into_iter()
+                .find(|next| self.cache.get(next, player.opponent(), ply).is_some_and(|entry| entry.value == -value));
+            match next {
+                Some(next) => {
+                    line.push(next.clone());
+                    state = next;
+                    player = player.opponent();
+                    value = -value;
+                },
+                None => break,
+            }
+        }
+        line
+    }
+
+    /// Search state to the given limit and return all successor states with the best ones exact
+    fn search_root(&mut self, state: &State, next_states: &[State], player: Player, guess: i16, limit: u8) -> Vec<Variation> {
+        let value = self.mtdf(state, player, guess, limit.saturating_add(1));
+
+        let mut variations = Vec::with_capacity(next_states.len());
+        for next_state in next_states {
+            // A successor state is among the best if its value is at most -value
+            let next_value = self.value(next_state, player.opponent(), 1 - value, limit, 1);
+            if self.aborted {
+                break;
+            }
+            let line = if next_value <= -value {
+                self.principal_variation(next_state, player.opponent(), -value, limit)
+            } else {
+                Vec::new()
+            };
+            variations.push(Variation::new(-next_value.max(-value), next_state, &line));
+        }
+        variations
+    }
+}
+
+impl ArtificialIntelligence for Mtdf {
+    type Counter = Counter;
+
+    fn best_moves(&mut self, state: State, player: Player) -> ArtificialIntelligenceResult<Counter> {
+        self.counter = Counter::new();
+        self.cache.new_search();
+
+        self.aborted = false;
+        // With iterative deepening the first iteration always completes to have a move to return
+        self.abortable = !self.options.iterative_deepening;
+        self.deadline = self.options.time_limit.map(|time_limit| Instant::now() + time_limit);
+
+        let start = if self.options.iterative_deepening { 0 } else { self.options.limit };
+
+        let next_states = state.next_states(player);
+
+        let mut guess = 0;
+        let mut values = None;
+        for limit in start..=self.options.limit {
+            let iteration = self.search_root(&state, &next_states, player, guess, limit);
+            if self.aborted {
+                break;
+            }
+            guess = iteration.iter()
+                .map(|variation| variation.value())
+                .max().expect("Unable to find maximum");
+            publish(&self.progress, limit, &iteration);
+            values = Some(iteration);
+            self.counter.depth = limit;
+
+            self.abortable = true;
+            if self.should_stop() {
+                break;
+            }
+        }
+        // Without iterative deepening an aborted search has no values
+        let values = values.unwrap_or_else(|| {
+            let ordered = self.order_moves(next_states, player, 0);
+            fallback(&ordered, |next_state| if next_state.finished(player.opponent()) {
+                -next_state.utility(player.opponent(), 1)
+            } else {
+                -next_state.heuristic(player.opponent(), &self.options.weights)
+            })
+        });
+
+        self.counter.cache_usage = self.cache.usage();
+
+        ArtificialIntelligenceResult::new(&values, 1, self.counter)
+            .select(self.options.selection, &state, player)
+    }
+
+    fn set_stop(&mut self, stop: Option<Arc<AtomicBool>>) {
+        self.stop = stop;
+    }
+
+    fn set_progress(&mut self, progress: Option<Arc<Mutex<Option<Progress>>>>) {
+        self.progress = progress;
+    }
+}
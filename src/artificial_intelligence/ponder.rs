@@ -0,0 +1,83 @@
+use std::{sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}}, thread::{self, JoinHandle}};
+
+use crate::game::{player::Player, state::State};
+
+use super::{ArtificialIntelligence, ArtificialIntelligenceResult, search_handle::SearchHandle};
+
+type Pondered<AI> = (AI, Option<ArtificialIntelligenceResult<<AI as ArtificialIntelligence>::Counter>>);
+
+pub enum Resolution<AI: ArtificialIntelligence> {
+    /// The guess was right, the result of pondering is ready
+    Hit(AI, ArtificialIntelligenceResult<AI::Counter>),
+    /// The guess was wrong, a search of the actual position is running
+    Miss(SearchHandle<AI>),
+}
+
+/// Search during the turn of the opponent.
+///
+/// The background thread searches the expected reply of the opponent, unless it is known, and
+/// then the position after it, which also fills the cache of the artificial intelligence.
+pub struct Ponder<AI: ArtificialIntelligence> {
+    player: Player,
+    stop: Arc<AtomicBool>,
+    /// Expected reply of the opponent, once it is known
+    guess: Arc<Mutex<Option<State>>>,
+    thread: JoinHandle<Pondered<AI>>,
+}
+
+impl<AI> Ponder<AI>
+where
+    AI: ArtificialIntelligence + Send + 'static,
+    AI::Counter: Send + 'static,
+{
+    /// Start pondering on state, where the opponent of player is to move.
+    /// Without a guess, like the second state of the principal variation, the reply is searched first.
+    pub fn start(mut ai: AI, state: State, player: Player, guess: Option<State>) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        ai.set_stop(Some(stop.clone()));
+
+        let guess = Arc::new(Mutex::new(guess));
+        let thread = {
+            let stop = stop.clone();
+            let guess = guess.clone();
+            thread::spawn(move || {
+                let mut result = None;
+                let known = guess.lock().expect("Guess lock poisoned").clone();
+                let reply = match known {
+                    Some(reply) => Some(reply),
+                    None if !state.finished(player.opponent()) => {
+                        let reply = ai.best_moves(state, player.opponent()).chosen().clone();
+                        *guess.lock().expect("Guess lock poisoned") = Some(reply.clone());
+                        Some(reply)
+                    },
+                    None => None,
+                };
+                if let Some(reply) = reply {
+                    if !stop.load(Ordering::Relaxed) && !reply.finished(player) {
+                        result = Some(ai.best_moves(reply, player));
+                    }
+                }
+                ai.set_stop(None);
+                (ai, result)
+            })
+        };
+
+        Ponder { player, stop, guess, thread }
+    }
+
+    /// Resolve pondering once the opponent played actual.
+    /// On a hit the search keeps running until its own limits end it, so it uses the rest of the move time,
+    /// which started with pondering. On a miss it is stopped and the actual position gets searched.
+    pub fn resolve(self, actual: &State) -> Resolution<AI> {
+        let hit = self.guess.lock().expect("Guess lock poisoned").as_ref() == Some(actual);
+        if !hit {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+        let (ai, result) = self.thread.join().expect("Ponder thread panicked");
+
+        match result {
+            Some(result) if hit => Resolution::Hit(ai, result),
+            _ => Resolution::Miss(SearchHandle::spawn(ai, actual.clone(), self.player)),
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use crate::game::state::State;
+
+/// Triangular array holding the best line found below every ply of the current path.
+///
+/// Lines end early at cache hits, as the cache does not store moves.
+#[derive(Default)]
+pub struct PrincipalVariation(Vec<Vec<State>>);
+
+impl PrincipalVariation {
+    /// Forget the line of ply, must be called when entering a node
+    pub fn clear(&mut self, ply: u8) {
+        let ply = ply as usize;
+        if self.0.len() <= ply + 1 {
+            self.0.resize(ply + 2, Vec::new());
+        }
+        self.0[ply].clear();
+        self.0[ply + 1].clear();
+    }
+
+    /// Make state followed by the line of the next ply the line of ply
+    pub fn update(&mut self, ply: u8, state: &State) {
+        let ply = ply as usize;
+        let (head, tail) = self.0.split_at_mut(ply + 1);
+        head[ply].clear();
+        head[ply].push(state.clone());
+        head[ply].extend_from_slice(&tail[0]);
+    }
+
+    pub fn get(&self, ply: u8) -> &[State] {
+        self.0.get(ply as usize).map_or(&[], |line| line)
+    }
+}
@@ -0,0 +1,227 @@
+use crate::game::{player::Player, state::State};
+
+/// Proof and disproof number of solved nodes
+const INFINITY: u32 = u32::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Won,
+    Lost,
+    /// Neither side can avoid repeating a position
+    Drawn,
+    /// The node cap was hit before the position got solved
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Counter {
+    /// Nodes created by both searches
+    pub nodes: u32,
+    pub expanded: u32,
+    /// Plies of the deepest node
+    pub depth: u32,
+}
+
+impl Counter {
+    fn new() -> Counter {
+        Counter {
+            nodes: 0,
+            expanded: 0,
+            depth: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ProofNumberOptions {
+    /// Give up with an unknown outcome once a search tree has this many nodes
+    pub max_nodes: usize,
+}
+
+impl Default for ProofNumberOptions {
+    fn default() -> Self {
+        ProofNumberOptions {
+            max_nodes: 500_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Solution {
+    pub outcome: Outcome,
+    /// Successor state reaching the outcome, only known for won and drawn positions
+    pub proof_move: Option<State>,
+    pub counter: Counter,
+}
+
+/// What the searching player tries to prove
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Goal {
+    Win,
+    /// Win or draw
+    NotLose,
+}
+
+struct Node {
+    state: State,
+    /// Player to move in state
+    player: Player,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    depth: u32,
+    proof: u32,
+    disproof: u32,
+}
+
+/// Proof-number search: expand the most proving node until the goal is proven or disproven.
+///
+/// The searching player tries to prove a win first and a draw second, where a position
+/// repeating one of its ancestors counts as a draw. There is no depth limit, only a node cap.
+pub struct ProofNumberSearch {
+    options: ProofNumberOptions,
+    counter: Counter,
+    /// Tree in an arena, the root is the first node
+    nodes: Vec<Node>,
+    player: Player,
+    goal: Goal,
+}
+
+impl ProofNumberSearch {
+    pub fn new(options: ProofNumberOptions) -> Self {
+        ProofNumberSearch {
+            options,
+            counter: Counter::new(),
+            nodes: Vec::new(),
+            player: Player::White,
+            goal: Goal::Win,
+        }
+    }
+
+    /// Solve state with player to move
+    pub fn solve(&mut self, state: State, player: Player) -> Solution {
+        self.counter = Counter::new();
+        self.player = player;
+
+        let (outcome, proof_move) = match self.prove(&state, Goal::Win) {
+            Some(true) => (Outcome::Won, self.proof_move()),
+            Some(false) => match self.prove(&state, Goal::NotLose) {
+                Some(true) => (Outcome::Drawn, self.proof_move()),
+                Some(false) => (Outcome::Lost, None),
+                None => (Outcome::Unknown, None),
+            },
+            None => (Outcome::Unknown, None),
+        };
+        self.nodes.clear();
+
+        Solution { outcome, proof_move, counter: self.counter }
+    }
+
+    /// Whether the goal holds, none if the node cap was hit
+    fn prove(&mut self, state: &State, goal: Goal) -> Option<bool> {
+        self.goal = goal;
+        self.nodes.clear();
+        self.add(state.clone(), self.player, None);
+
+        while self.nodes[0].proof != 0 && self.nodes[0].disproof != 0 {
+            if self.nodes.len() >= self.options.max_nodes {
+                return None;
+            }
+            let index = self.most_proving();
+            self.expand(index);
+            self.update(index);
+        }
+        Some(self.nodes[0].proof == 0)
+    }
+
+    /// Successor of the root proving the goal
+    fn proof_move(&self) -> Option<State> {
+        self.nodes[0].children.iter()
+            .find(|child| self.nodes[**child].proof == 0)
+            .map(|child| self.nodes[*child].state.clone())
+    }
+
+    /// Add a node with its proof and disproof numbers.
+    /// Unsolved leaves are harder to prove or disprove the more moves the player to move has.
+    fn add(&mut self, state: State, player: Player, parent: Option<usize>) -> usize {
+        let depth = parent.map_or(0, |parent| self.nodes[parent].depth + 1);
+        self.counter.nodes += 1;
+        self.counter.depth = self.counter.depth.max(depth);
+
+        self.nodes.push(Node { state, player, parent, children: Vec::new(), depth, proof: 1, disproof: 1 });
+        let index = self.nodes.len() - 1;
+
+        let moves = self.nodes[index].state.next_states(player).len() as u32;
+        let node = &self.nodes[index];
+        let (proof, disproof) = if self.repeats(index) {
+            if self.goal == Goal::NotLose { (0, INFINITY) } else { (INFINITY, 0) }
+        } else if node.state.finished(player) {
+            // The player to move lost
+            if player != self.player { (0, INFINITY) } else { (INFINITY, 0) }
+        } else if player == self.player {
+            (1, moves)
+        } else {
+            (moves, 1)
+        };
+        let node = &mut self.nodes[index];
+        (node.proof, node.disproof) = (proof, disproof);
+        index
+    }
+
+    /// Check whether the position of index occurred on the path from the root
+    fn repeats(&self, index: usize) -> bool {
+        let node = &self.nodes[index];
+        let mut ancestor = node.parent;
+        while let Some(parent) = ancestor {
+            let other = &self.nodes[parent];
+            if other.state == node.state && other.player == node.player {
+                return true;
+            }
+            ancestor = other.parent;
+        }
+        false
+    }
+
+    /// Follow the children with the lowest proof numbers at nodes of the searching player
+    /// and the lowest disproof numbers at nodes of the opponent down to a leaf
+    fn most_proving(&self) -> usize {
+        let mut index = 0;
+        while !self.nodes[index].children.is_empty() {
+            let node = &self.nodes[index];
+            let child = if node.player == self.player {
+                node.children.iter().min_by_key(|child| self.nodes[**child].proof)
+            } else {
+                node.children.iter().min_by_key(|child| self.nodes[**child].disproof)
+            };
+            index = *child.expect("Node has children");
+        }
+        index
+    }
+
+    fn expand(&mut self, index: usize) {
+        self.counter.expanded += 1;
+        let (state, player) = (self.nodes[index].state.clone(), self.nodes[index].player);
+        for next_state in state.next_states(player) {
+            let child = self.add(next_state, player.opponent(), Some(index));
+            self.nodes[index].children.push(child);
+        }
+    }
+
+    /// Recompute the numbers from index up to the root
+    fn update(&mut self, index: usize) {
+        let mut current = Some(index);
+        while let Some(index) = current {
+            let node = &self.nodes[index];
+            let proofs = node.children.iter().map(|child| self.nodes[*child].proof);
+            let disproofs = node.children.iter().map(|child| self.nodes[*child].disproof);
+            let (proof, disproof) = if node.player == self.player {
+                (proofs.min(), Some(disproofs.fold(0, u32::saturating_add)))
+            } else {
+                (Some(proofs.fold(0, u32::saturating_add)), disproofs.min())
+            };
+            let node = &mut self.nodes[index];
+            node.proof = proof.expect("Node has children");
+            node.disproof = disproof.expect("Node has children");
+            current = node.parent;
+        }
+    }
+}
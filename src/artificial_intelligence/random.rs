@@ -0,0 +1,47 @@
+use std::sync::{Arc, Mutex, atomic::AtomicBool};
+
+use crate::game::{player::Player, state::State};
+
+use super::{ArtificialIntelligence, ArtificialIntelligenceResult, Progress, Variation, rng::Rng};
+
+/// A random choice does not visit any states
+#[derive(Debug, Clone, Copy)]
+pub struct Counter;
+
+#[derive(Debug, Clone, Default)]
+pub struct RandomOptions {
+    pub seed: u64,
+}
+
+/// Play a uniformly random legal move.
+///
+/// The chosen move has the value 0, all others -1.
+pub struct Random {
+    rng: Rng,
+}
+
+impl Random {
+    pub fn new(options: RandomOptions) -> Self {
+        Random {
+            rng: Rng::new(options.seed),
+        }
+    }
+}
+
+impl ArtificialIntelligence for Random {
+    type Counter = Counter;
+
+    fn best_moves(&mut self, state: State, player: Player) -> ArtificialIntelligenceResult<Counter> {
+        let next_states = state.next_states(player);
+        let chosen = self.rng.below(next_states.len());
+        let variations: Vec<Variation> = next_states.iter().enumerate()
+            .map(|(index, next_state)| Variation::new(if index == chosen { 0 } else { -1 }, next_state, &[]))
+            .collect();
+        ArtificialIntelligenceResult::new(&variations, 1, Counter)
+    }
+
+    /// Choosing a move is instant, there is nothing to stop
+    fn set_stop(&mut self, _stop: Option<Arc<AtomicBool>>) {}
+
+    fn set_progress(&mut self, _progress: Option<Arc<Mutex<Option<Progress>>>>) {}
+}
@@ -0,0 +1,56 @@
+use std::{sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}}, thread::{self, JoinHandle}};
+
+use crate::game::{player::Player, state::State};
+
+use super::{ArtificialIntelligence, ArtificialIntelligenceResult, Progress};
+
+/// A search running on a background thread.
+///
+/// The artificial intelligence is moved into the thread and handed back by `join`,
+/// so its cache survives for the next search.
+pub struct SearchHandle<AI: ArtificialIntelligence> {
+    stop: Arc<AtomicBool>,
+    progress: Arc<Mutex<Option<Progress>>>,
+    thread: JoinHandle<(AI, ArtificialIntelligenceResult<AI::Counter>)>,
+}
+
+impl<AI> SearchHandle<AI>
+where
+    AI: ArtificialIntelligence + Send + 'static,
+    AI::Counter: Send + 'static,
+{
+    pub fn spawn(mut ai: AI, state: State, player: Player) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let progress = Arc::new(Mutex::new(None));
+        ai.set_stop(Some(stop.clone()));
+        ai.set_progress(Some(progress.clone()));
+
+        let thread = thread::spawn(move || {
+            let result = ai.best_moves(state, player);
+            ai.set_stop(None);
+            ai.set_progress(None);
+            (ai, result)
+        });
+
+        SearchHandle { stop, progress, thread }
+    }
+
+    /// Best result found so far, `None` until the first result is available
+    pub fn progress(&self) -> Option<Progress> {
+        self.progress.lock().expect("Progress lock poisoned").clone()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Ask the search to stop, returns immediately
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// Wait for the search to finish
+    pub fn join(self) -> (AI, ArtificialIntelligenceResult<AI::Counter>) {
+        self.thread.join().expect("Search thread panicked")
+    }
+}
@@ -0,0 +1,120 @@
+use std::{sync::{Arc, Mutex, atomic::AtomicBool}, time::Duration};
+
+use crate::game::{player::Player, state::State};
+
+use super::{ArtificialIntelligence, ArtificialIntelligenceResult, Progress, alpha_beta_pruning::{AlphaBetaPruning, AlphaBetaPruningOptions, Counter}, rng::Rng};
+
+pub const MIN_LEVEL: u8 = 1;
+pub const MAX_LEVEL: u8 = 20;
+/// From this level on the random baseline should never score
+const SATURATION_LEVEL: u8 = 12;
+
+#[derive(Debug, Clone)]
+pub struct SkillOptions {
+    /// Between `MIN_LEVEL` and `MAX_LEVEL`, higher levels play stronger
+    pub level: u8,
+    pub seed: u64,
+    /// Stop the search after this duration, the level still caps the depth
+    pub time_limit: Option<Duration>,
+}
+
+impl Default for SkillOptions {
+    fn default() -> Self {
+        SkillOptions {
+            level: MAX_LEVEL,
+            seed: 0,
+            time_limit: None,
+        }
+    }
+}
+
+/// Imperfections of a level
+#[derive(Debug, Clone, Copy)]
+pub struct Level {
+    pub limit: u8,
+    /// Maximal random change of the values of the root moves
+    pub noise: i32,
+    /// Chance to play the second best move in per mille
+    pub mistakes: u32,
+}
+
+/// Noise of the levels below `SATURATION_LEVEL`, tuned to `Level::target_score` with `bench skill 200`.
+/// The noise of the weakest level exceeds every value, so it plays at random.
+const NOISE: [i32; (SATURATION_LEVEL - MIN_LEVEL) as usize] = [1_000_000, 11585, 8192, 512, 256, 128, 118, 64, 45, 32, 23];
+
+impl Level {
+    /// Higher levels never search less deep, add more noise or make more mistakes
+    pub fn new(level: u8) -> Self {
+        let level = level.clamp(MIN_LEVEL, MAX_LEVEL);
+        Level {
+            limit: 1 + (level - MIN_LEVEL) / 4,
+            noise: NOISE.get((level - MIN_LEVEL) as usize).copied().unwrap_or(0),
+            mistakes: 25 * SATURATION_LEVEL.saturating_sub(level) as u32,
+        }
+    }
+
+    /// Share of the games the level should score against the random baseline, draws count half.
+    pub fn target_score(level: u8) -> f64 {
+        let level = level.clamp(MIN_LEVEL, MAX_LEVEL);
+        let progress = (level - MIN_LEVEL) as f64 / (SATURATION_LEVEL - MIN_LEVEL) as f64;
+        0.5 + 0.5 * progress.min(1.0).sqrt()
+    }
+}
+
+/// Alpha-beta pruning playing at a skill level.
+///
+/// Lower levels search less deep, add noise to the values of the root moves
+/// and deliberately play the second best move every now and then.
+/// The result only contains the chosen move with its value before the noise.
+pub struct Skill {
+    ai: AlphaBetaPruning,
+    level: Level,
+    rng: Rng,
+}
+
+impl Skill {
+    pub fn new(options: SkillOptions) -> Self {
+        let level = Level::new(options.level);
+        let imperfect = level.noise > 0 || level.mistakes > 0;
+        let ai = AlphaBetaPruning::new(AlphaBetaPruningOptions {
+            limit: level.limit,
+            time_limit: options.time_limit,
+            // The noise needs exact values of all root moves
+            multi_pv: if imperfect { usize::MAX } else { 1 },
+            ..Default::default()
+        });
+        Skill { ai, level, rng: Rng::new(options.seed) }
+    }
+}
+
+impl ArtificialIntelligence for Skill {
+    type Counter = Counter;
+
+    fn best_moves(&mut self, state: State, player: Player) -> ArtificialIntelligenceResult<Counter> {
+        let result = self.ai.best_moves(state, player);
+
+        let noise = self.level.noise;
+        let mut noisy: Vec<(i32, usize)> = result.variations().iter().enumerate()
+            .map(|(index, variation)| {
+                let offset = self.rng.below(2 * noise as usize + 1) as i32 - noise;
+                ((variation.value() as i32 + offset), index)
+            })
+            .collect();
+        // Stable, so ties keep the order of the search
+        noisy.sort_by_key(|(value, _)| std::cmp::Reverse(*value));
+
+        let mistake = noisy.len() > 1 && (self.rng.below(1000) as u32) < self.level.mistakes;
+        let (_, chosen) = noisy[if mistake { 1 } else { 0 }];
+
+        let variations = [result.variations()[chosen].clone()];
+        ArtificialIntelligenceResult::new(&variations, 1, *result.counter())
+    }
+
+    fn set_stop(&mut self, stop: Option<Arc<AtomicBool>>) {
+        self.ai.set_stop(stop);
+    }
+
+    fn set_progress(&mut self, progress: Option<Arc<Mutex<Option<Progress>>>>) {
+        self.ai.set_progress(progress);
+    }
+}
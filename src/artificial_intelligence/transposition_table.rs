@@ -0,0 +1,213 @@
+use std::sync::atomic::{AtomicU8, AtomicU64, Ordering};
+
+use crate::game::{player::Player, score, state::State};
+
+const BUCKET_SIZE: usize = 4;
+const SLOT_SIZE: usize = std::mem::size_of::<AtomicSlot>();
+/// Entries of older searches lose this much depth per generation when picking a victim
+const AGE_PENALTY: i16 = 8;
+/// Number of slots sampled to estimate the usage of the table
+const USAGE_SAMPLE: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    /// The value is exact
+    Exact,
+    /// The real value is at least the stored value (fail high)
+    Lower,
+    /// The real value is at most the stored value (fail low)
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Entry {
+    pub value: i16,
+    pub depth: u8,
+    pub bound: Bound,
+}
+
+/// A packed entry, `data == 0` marks an empty slot.
+///
+/// The data is laid out as `value (16) | depth (8) | bound (2) | unused (6) | generation (8)`.
+#[derive(Clone, Copy)]
+struct Slot {
+    key: u64,
+    data: u64,
+}
+
+impl Slot {
+    fn pack(key: u64, entry: Entry, generation: u8) -> Slot {
+        let bound: u64 = match entry.bound {
+            Bound::Exact => 1,
+            Bound::Lower => 2,
+            Bound::Upper => 3,
+        };
+        Slot {
+            key,
+            data: (entry.value as u16 as u64)
+                | (entry.depth as u64) << 16
+                | bound << 24
+                | (generation as u64) << 32,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data == 0
+    }
+
+    fn entry(&self) -> Entry {
+        Entry {
+            value: self.data as u16 as i16,
+            depth: (self.data >> 16) as u8,
+            bound: match (self.data >> 24) & 0b11 {
+                1 => Bound::Exact,
+                2 => Bound::Lower,
+                _ => Bound::Upper,
+            },
+        }
+    }
+
+    fn generation(&self) -> u8 {
+        (self.data >> 32) as u8
+    }
+}
+
+/// A slot shared between threads without locks.
+///
+/// The key is stored xor the data, so a slot torn by concurrent writes
+/// does not match its key anymore and is ignored.
+#[derive(Default)]
+struct AtomicSlot {
+    check: AtomicU64,
+    data: AtomicU64,
+}
+
+impl AtomicSlot {
+    fn load(&self) -> Slot {
+        let data = self.data.load(Ordering::Relaxed);
+        let key = self.check.load(Ordering::Relaxed) ^ data;
+        Slot { key, data }
+    }
+
+    fn store(&self, slot: Slot) {
+        self.check.store(slot.key ^ slot.data, Ordering::Relaxed);
+        self.data.store(slot.data, Ordering::Relaxed);
+    }
+}
+
+/// Fixed-size cache for search results of positions, which can be shared between threads.
+///
+/// Slots are grouped in buckets of four. A new entry replaces the same position,
+/// an empty slot or the slot with the lowest depth, where entries of older
+/// searches are penalized by their age.
+pub struct TranspositionTable {
+    slots: Vec<AtomicSlot>,
+    generation: AtomicU8,
+}
+
+impl TranspositionTable {
+    /// Allocate a table of (at most) the given size in megabytes
+    pub fn new(megabytes: usize) -> Self {
+        let buckets = megabytes * 1024 * 1024 / (SLOT_SIZE * BUCKET_SIZE);
+        TranspositionTable {
+            slots: (0..buckets * BUCKET_SIZE).map(|_| AtomicSlot::default()).collect(),
+            generation: AtomicU8::new(0),
+        }
+    }
+
+    /// Age all stored entries, should be called before every search
+    pub fn new_search(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Entry of state at ply after the root, decisive values are relative to the root
+    pub fn get(&self, state: &State, player: Player, ply: u8) -> Option<Entry> {
+        let key = Self::key(state, player);
+        self.bucket(key)?.iter()
+            .map(AtomicSlot::load)
+            .find(|slot| !slot.is_empty() && slot.key == key)
+            .map(|slot| {
+                let entry = slot.entry();
+                Entry { value: score::to_root(entry.value, ply), ..entry }
+            })
+    }
+
+    /// Store the entry of state at ply after the root, decisive values are stored relative to the state
+    pub fn set(&self, state: &State, player: Player, ply: u8, entry: Entry) {
+        let entry = Entry { value: score::to_node(entry.value, ply), ..entry };
+        let key = Self::key(state, player);
+        let generation = self.generation.load(Ordering::Relaxed);
+        let start = match self.index(key) {
+            Some(start) => start,
+            None => return,
+        };
+        let bucket: [Slot; BUCKET_SIZE] = std::array::from_fn(|index| self.slots[start + index].load());
+
+        let index = match bucket.iter().position(|slot| !slot.is_empty() && slot.key == key) {
+            Some(index) => {
+                let slot = bucket[index];
+                // Keep deeper results of the current search unless the new one is exact
+                if slot.generation() == generation
+                    && slot.entry().depth > entry.depth
+                    && entry.bound != Bound::Exact {
+                    return;
+                }
+                index
+            },
+            None => (0..BUCKET_SIZE)
+                .min_by_key(|index| {
+                    let slot = bucket[*index];
+                    if slot.is_empty() {
+                        i16::MIN
+                    } else {
+                        let age = generation.wrapping_sub(slot.generation()) as i16;
+                        slot.entry().depth as i16 - AGE_PENALTY * age
+                    }
+                })
+                .expect("Bucket is not empty"),
+        };
+        self.slots[start + index].store(Slot::pack(key, entry, generation));
+    }
+
+    /// Share of slots filled by the current search in per mille
+    pub fn usage(&self) -> u32 {
+        let sample = self.slots.len().min(USAGE_SAMPLE);
+        if sample == 0 {
+            return 0;
+        }
+        let generation = self.generation.load(Ordering::Relaxed);
+        let used = self.slots[..sample].iter()
+            .map(AtomicSlot::load)
+            .filter(|slot| !slot.is_empty() && slot.generation() == generation)
+            .count();
+        (used * 1000 / sample) as u32
+    }
+
+    /// Unique key of state with player to move
+    pub fn key(state: &State, player: Player) -> u64 {
+        // The state only uses the lower 56 bits
+        let key: u64 = state.into();
+        match player {
+            Player::White => key | 1 << 63,
+            Player::Black => key,
+        }
+    }
+
+    fn index(&self, key: u64) -> Option<usize> {
+        let buckets = (self.slots.len() / BUCKET_SIZE) as u64;
+        if buckets == 0 {
+            return None;
+        }
+        // Finalizer of splitmix64 to spread the structured keys
+        let mut hash = key;
+        hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        hash ^= hash >> 31;
+        Some((hash % buckets) as usize * BUCKET_SIZE)
+    }
+
+    fn bucket(&self, key: u64) -> Option<&[AtomicSlot]> {
+        let index = self.index(key)?;
+        Some(&self.slots[index..index + BUCKET_SIZE])
+    }
+}
@@ -0,0 +1,203 @@
+use std::time::Instant;
+
+use crate::{artificial_intelligence::{ArtificialIntelligence, ArtificialIntelligenceResult, Selection, alpha_beta_pruning::{AlphaBetaPruning, AlphaBetaPruningOptions, Counter}, minimax::{Minimax, MinimaxOptions}, random::{Random, RandomOptions}, skill::{Level, MAX_LEVEL, MIN_LEVEL, Skill, SkillOptions}}, game::{heuristic::{Evaluator, HeuristicWeights, TaperedWeights}, player::Player, state::State}};
+
+/// Reference positions given by the moves played from the start, white begins
+pub const POSITIONS: [&[&str]; 5] = [
+    &[],
+    &["d2", "d6", "b4", "f4", "b6"],
+    &["a7", "d7", "g7", "g4", "g1", "d1", "a1", "a4", "b4", "f4", "d2", "d6"],
+    &["a7", "d7", "g7", "g4", "g1", "d1", "a1", "a4", "b4", "f4", "d2", "d6", "c3", "e5", "e3", "c5", "f6", "b2"],
+    &["a7", "d7", "g7", "g4", "g1", "d1", "a1", "a4", "b4", "f4", "d2", "d6", "c3", "e5", "e3", "c5", "f6", "b2", "b4-b6", "d6-d5xb6"],
+];
+
+/// Play the moves from the start, panics on illegal moves
+pub fn position(moves: &[&str]) -> (State, Player) {
+    let mut state = State::new();
+    let mut player = Player::White;
+    for m in moves {
+        state = state.next_states(player).into_iter()
+            .find(|next| state.get_move(next, player).to_string() == *m)
+            .unwrap_or_else(|| panic!("Illegal move {} in\n{}", m, state));
+        player = player.opponent();
+    }
+    (state, player)
+}
+
+/// Compare visited nodes of plain alpha-beta pruning and principal variation search
+pub fn principal_variation_search(limit: u8) {
+    println!("position | value plain | visited plain | value pvs | visited pvs | re-searches");
+    for (index, moves) in POSITIONS.iter().enumerate() {
+        let (state, player) = position(moves);
+        let results: Vec<_> = [false, true].iter()
+            .map(|principal_variation_search| {
+                let mut ai: AlphaBetaPruning = AlphaBetaPruning::new(AlphaBetaPruningOptions {
+                    limit,
+                    principal_variation_search: *principal_variation_search,
+                    ..Default::default()
+                });
+                ai.best_moves(state.clone(), player)
+            })
+            .collect();
+        println!(
+            "{:>8} | {:>11} | {:>13} | {:>9} | {:>11} | {:>11}",
+            index,
+            results[0].value(), results[0].counter().visisted,
+            results[1].value(), results[1].counter().visisted,
+            results[1].counter().re_search,
+        );
+    }
+}
+
+/// Names of the pruning configurations with late move reductions and futility pruning, the plain search first
+const PRUNING_CONFIGURATIONS: [(&str, bool, bool); 4] = [("plain", false, false), ("lmr", true, false), ("futility", false, true), ("both", true, true)];
+
+fn pruning_search(state: &State, player: Player, limit: u8, late_move_reductions: bool, futility_pruning: bool) -> ArtificialIntelligenceResult<Counter> {
+    let mut ai: AlphaBetaPruning = AlphaBetaPruning::new(AlphaBetaPruningOptions {
+        limit,
+        late_move_reductions,
+        futility_pruning,
+        ..Default::default()
+    });
+    ai.best_moves(state.clone(), player)
+}
+
+/// Check that late move reductions and futility pruning choose the same moves as the plain search.
+/// Returns whether every position passed.
+pub fn pruning_regression(limit: u8) -> bool {
+    println!("position | configuration |     move | value | visited | reductions | futility pruned");
+    let mut passed = true;
+    for (index, moves) in POSITIONS.iter().enumerate() {
+        let (state, player) = position(moves);
+        let mut expected = None;
+        for (name, late_move_reductions, futility_pruning) in PRUNING_CONFIGURATIONS {
+            let result = pruning_search(&state, player, limit, late_move_reductions, futility_pruning);
+            let chosen = state.get_move(result.chosen(), player).to_string();
+            let changed = expected.get_or_insert_with(|| chosen.clone()) != &chosen;
+            passed &= !changed;
+            println!(
+                "{:>8} | {:>13} | {:>8} | {:>5} | {:>7} | {:>10} | {:>15}{}",
+                index, name, chosen, result.value(), result.counter().visisted,
+                result.counter().reductions, result.counter().futility_pruned,
+                if changed { " CHANGED" } else { "" },
+            );
+        }
+    }
+    passed
+}
+
+/// Compare sequential minimax with root splitting over several threads
+pub fn minimax_threads(limit: u8) {
+    println!("position | threads | value | visited | milliseconds");
+    for (index, moves) in POSITIONS.iter().enumerate() {
+        let (state, player) = position(moves);
+        for threads in [1, 2, 4] {
+            let mut ai: Minimax = Minimax::new(MinimaxOptions {
+                limit,
+                threads,
+                ..Default::default()
+            });
+            let start = Instant::now();
+            let result = ai.best_moves(state.clone(), player);
+            println!(
+                "{:>8} | {:>7} | {:>5} | {:>7} | {:>12}",
+                index, threads, result.value(), result.counter().visisted, start.elapsed().as_millis(),
+            );
+        }
+    }
+}
+
+/// Games still running after this many plies count as draws
+const MAX_PLIES: u32 = 200;
+
+/// Play a game from the start and return the winner, none for a draw
+pub fn game(white: &mut impl ArtificialIntelligence, black: &mut impl ArtificialIntelligence) -> Option<Player> {
+    let mut state = State::new();
+    let mut player = Player::White;
+    for _ in 0..MAX_PLIES {
+        if state.finished(player) {
+            return Some(if state.utility(player, 0) > 0 { player } else { player.opponent() });
+        }
+        state = match player {
+            Player::White => white.best_moves(state, player).chosen().clone(),
+            Player::Black => black.best_moves(state, player).chosen().clone(),
+        };
+        player = player.opponent();
+    }
+    None
+}
+
+/// Compare the score of every skill level against the random baseline with its target, draws count half
+pub fn skill_levels(games: u32) {
+    println!("level | limit |   noise | mistakes | score | target");
+    for level in MIN_LEVEL..=MAX_LEVEL {
+        let mut points = 0.0;
+        for game_index in 0..games {
+            let seed = game_index as u64;
+            let mut skill = Skill::new(SkillOptions { level, seed, ..Default::default() });
+            let mut random = Random::new(RandomOptions { seed });
+            // Alternate the colors
+            let (winner, player) = if game_index.is_multiple_of(2) {
+                (game(&mut skill, &mut random), Player::White)
+            } else {
+                (game(&mut random, &mut skill), Player::Black)
+            };
+            points += match winner {
+                Some(winner) if winner == player => 1.0,
+                Some(_) => 0.0,
+                None => 0.5,
+            };
+        }
+        let parameters = Level::new(level);
+        println!(
+            "{:>5} | {:>5} | {:>7} | {:>8} | {:>5.2} | {:>6.2}",
+            level, parameters.limit, parameters.noise, parameters.mistakes, points / games as f64, Level::target_score(level),
+        );
+    }
+}
+
+/// Alpha-beta pruning with an evaluator, which picks randomly among equally good moves to vary the games
+fn evaluation_player<E: Evaluator + Default>(evaluator: E, limit: u8, seed: u64) -> AlphaBetaPruning<E> {
+    AlphaBetaPruning::new(AlphaBetaPruningOptions {
+        limit,
+        evaluator,
+        cache_size: 1,
+        selection: Selection::Random(seed),
+        ..Default::default()
+    })
+}
+
+/// Play the tapered weights against the single weight set with alternating colors, draws count half
+pub fn tapered_weights(weights: &TaperedWeights, games: u32, limit: u8) {
+    let (mut wins, mut draws, mut losses) = (0, 0, 0);
+    for game_index in 0..games {
+        let seed = game_index as u64;
+        let mut tapered = evaluation_player(weights.clone(), limit, seed);
+        let mut single = evaluation_player(HeuristicWeights::default(), limit, seed + games as u64);
+        let (winner, player) = if game_index.is_multiple_of(2) {
+            (game(&mut tapered, &mut single), Player::White)
+        } else {
+            (game(&mut single, &mut tapered), Player::Black)
+        };
+        match winner {
+            Some(winner) if winner == player => wins += 1,
+            Some(_) => losses += 1,
+            None => draws += 1,
+        }
+    }
+    let score = (wins as f64 + draws as f64 / 2.0) / games as f64;
+    println!("limit {} | wins {} | draws {} | losses {} | score {:.2}", limit, wins, draws, losses, score);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lower than `BENCHMARK_LIMIT`, so the suite stays fast in debug builds
+    const REGRESSION_LIMIT: u8 = 3;
+
+    #[test]
+    fn pruning_keeps_moves() {
+        assert!(pruning_regression(REGRESSION_LIMIT));
+    }
+}
@@ -15,9 +15,9 @@ impl Display for Cell {
     }
 }
 
-impl Into<&str> for &Cell {
-    fn into(self) -> &'static str {
-        match self {
+impl From<&Cell> for &str {
+    fn from(cell: &Cell) -> Self {
+        match cell {
             Cell::White => "w",
             Cell::Black => "b",
             Cell::Empty => " ",
@@ -25,12 +25,12 @@ impl Into<&str> for &Cell {
     }
 }
 
-impl Into<Player> for Cell {
-    fn into(self) -> Player {
-        match self {
+impl From<Cell> for Player {
+    fn from(cell: Cell) -> Self {
+        match cell {
             Cell::White => Player::White,
             Cell::Black => Player::Black,
-            _ => panic!("Unable to convert '{}' into Player", self),
+            _ => panic!("Unable to convert '{}' into Player", cell),
         }
     }
 }
@@ -50,7 +50,7 @@ impl State {
 
     pub fn get_cells(&self, cell: Cell) -> Vec<Coordinate> {
         (0..24)
-            .map(|index| Coordinate::from(index))
+            .map(Coordinate::from)
             .filter(|coordinate| self.get(coordinate) == cell)
             .collect()
     }
@@ -2,9 +2,16 @@ use std::fmt::Display;
 
 use super::{masks::offset_board, player::Player};
 
-#[derive(Debug, Eq, PartialEq, Hash)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub struct Coordinate(u8);
 
+/// Names of the points in algebraic notation, ordered by index
+const NOTATION: [&str; 24] = [
+    "a7", "d7", "g7", "g4", "g1", "d1", "a1", "a4",
+    "b6", "d6", "f6", "f4", "f2", "d2", "b2", "b4",
+    "c5", "d5", "e5", "e4", "e3", "d3", "c3", "c4",
+];
+
 impl From<u8> for Coordinate {
     fn from(from: u8) -> Self {
         Coordinate(from)
@@ -22,6 +29,11 @@ impl Coordinate {
         Coordinate(ring * 8 + cell)
     }
 
+    /// Name of the point in algebraic notation like `d2`
+    pub fn notation(&self) -> &'static str {
+        NOTATION[self.0 as usize]
+    }
+
     pub fn as_mask(&self, player: Player) -> u64 {
         (1 << self.0) << offset_board(player)
     }
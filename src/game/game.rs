@@ -1,4 +1,4 @@
-use super::{player::Player, state::State, cell::Cell, phase::Phase};
+use super::{player::Player, state::State, cell::Cell, phase::Phase, score::win_in};
 
 
 impl State {
@@ -42,7 +42,7 @@ impl State {
                         cells_empty.iter()
                             .flat_map(|to| {
                                 let mut state = self.clone();
-                                state.move_to(&from, &to, player);
+                                state.move_to(&from, to, player);
                                 state.pound_mills(player, &mills_before)
                             })
                             .collect()
@@ -58,15 +58,16 @@ impl State {
         || self.next_states(player).is_empty()
     }
 
-    pub fn utility(&self, player: Player) -> i16 {
-        if self.has_enough_stones(player) {
-            -100
+    /// Value of a finished state at the given ply after the root, earlier ends weigh more
+    pub fn utility(&self, player: Player, ply: u8) -> i16 {
+        if !self.has_enough_stones(player) {
+            -win_in(ply)
         }
-        else if self.has_enough_stones(player.opponent()) {
-            100
+        else if !self.has_enough_stones(player.opponent()) {
+            win_in(ply)
         }
         else if self.next_states(player).is_empty() {
-            -100
+            -win_in(ply)
         }
         else {
             0
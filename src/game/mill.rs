@@ -1,6 +1,6 @@
-use std::{collections::HashSet};
+use std::collections::{BTreeSet, HashSet};
 
-use super::{cell::Cell, coordinate::Coordinate, masks::{MASK_MILLS, mask_board, offset_board}, player::Player, state::State};
+use super::{cell::Cell, coordinate::Coordinate, masks::{MASK_MILLS, mask_board, offset_board}, phase::Phase, player::Player, state::State};
 
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub struct Mill(usize);
@@ -14,10 +14,14 @@ impl Mill {
         MASK_MILLS[self.0] << offset_board(player)
     }
 
+    pub fn contains(&self, coordinate: &Coordinate) -> bool {
+        self.as_mask(Player::Black) & coordinate.as_mask(Player::Black) != 0
+    }
+
     pub fn get_coordinates(&self) -> Vec<Coordinate> {
         (0..24)
             .filter(|index| self.as_mask(Player::Black) & (1 << index) != 0)
-            .map(|index| Coordinate::from(index))
+            .map(Coordinate::from)
             .take(3)
             .collect()
     }
@@ -35,18 +39,77 @@ impl State {
 
     pub fn get_mills(&self, player: Player) -> Vec<Mill> {
         (0..16)
-            .map(|index| Mill::new(index))
-            .filter(|mill| self.has_mill(player, &mill))
+            .map(Mill::new)
+            .filter(|mill| self.has_mill(player, mill))
             .collect()
     }
 
     pub fn get_uncompleted_mills(&self, player: Player) -> Vec<Mill> {
         (0..16)
-            .map(|index| Mill::new(index))
-            .filter(|mill| self.has_uncompleted_mill(player, &mill))
+            .map(Mill::new)
+            .filter(|mill| self.has_uncompleted_mill(player, mill))
             .collect()
     }
 
+    /// Check if player can close a mill with the next move
+    pub fn can_close_mill(&self, player: Player) -> bool {
+        let phase = self.phase(player);
+        self.get_uncompleted_mills(player).iter().any(|mill| {
+            match phase {
+                // Any other stone can fly in
+                Phase::Placing | Phase::Flying => true,
+                Phase::Moving => mill.get_coordinates().iter()
+                    .filter(|coordinate| self.get(coordinate) == Cell::Empty)
+                    .any(|empty| {
+                        self.get_neighbours(empty, player.into()).iter()
+                            .any(|neighbour| !mill.contains(neighbour))
+                    }),
+            }
+        })
+    }
+
+    /// Check if player can open a mill by moving a stone, which closes another mill on its own (Zwickmühle)
+    pub fn has_double_mill(&self, player: Player) -> bool {
+        self.count_double_mills(player) > 0
+    }
+
+    /// Count the mills of player with a stone, which closes another mill by moving to a neighbouring empty point.
+    /// Only while moving, placing and flying stones do not open and close mills by sliding.
+    pub fn count_double_mills(&self, player: Player) -> usize {
+        if !matches!(self.phase(player), Phase::Moving) {
+            return 0;
+        }
+        let uncompleted_mills = self.get_uncompleted_mills(player);
+        self.get_mills(player).iter()
+            .filter(|mill| {
+                mill.get_coordinates().iter().any(|from| {
+                    self.get_neighbours(from, Cell::Empty).iter().any(|to| {
+                        uncompleted_mills.iter().any(|other| other.contains(to) && !other.contains(from))
+                    })
+                })
+            })
+            .count()
+    }
+
+    /// Count the uncompleted mills, which player can close by moving a stone
+    /// and the opponent cannot block, because none of its stones can move to the empty point.
+    /// Only while both players move, placing and flying stones reach every empty point.
+    pub fn count_open_mills(&self, player: Player) -> usize {
+        if !matches!(self.phase(player), Phase::Moving) || !matches!(self.phase(player.opponent()), Phase::Moving) {
+            return 0;
+        }
+        self.get_uncompleted_mills(player).iter()
+            .filter(|mill| {
+                mill.get_coordinates().iter()
+                    .filter(|coordinate| self.get(coordinate) == Cell::Empty)
+                    .any(|empty| {
+                        self.get_neighbours(empty, player.opponent().into()).is_empty()
+                            && self.get_neighbours(empty, player.into()).iter().any(|neighbour| !mill.contains(neighbour))
+                    })
+            })
+            .count()
+    }
+
     pub fn get_poundable_stones(&self, player: Player) -> Vec<Coordinate> {
         let mut bits = self.0 & mask_board(player);
         // Remove all mills from the board
@@ -60,13 +123,14 @@ impl State {
         }
 
         (0..24)
-            .map(|index| Coordinate::from(index))
+            .map(Coordinate::from)
             .filter(|coordinate| (coordinate.as_mask(player) & bits) != 0)
             .collect()
     }
 
-    pub fn pound_stones(&self, opponent: Player, count: usize) -> HashSet<State> {
-        if count <= 0 {
+    /// All states after pounding count stones, in a reproducible order
+    pub fn pound_stones(&self, opponent: Player, count: usize) -> BTreeSet<State> {
+        if count == 0 {
             return vec![self.clone()].into_iter().collect();
         }
 
@@ -76,16 +140,15 @@ impl State {
                 state.place(&coordinate, Cell::Empty);
                 state
             })
-            .map(|state| state.pound_stones(opponent, count - 1))
-            .flatten()
+            .flat_map(|state| state.pound_stones(opponent, count - 1))
             .collect()
     }
 
     /// Pound a stone for every new mill from player
-    pub fn pound_mills(&self, player: Player, mills_before: &Vec<Mill>) -> HashSet<State> {
+    pub fn pound_mills(&self, player: Player, mills_before: &[Mill]) -> BTreeSet<State> {
         let mills = self.get_mills(player);
         let mills: HashSet<_> = mills.iter().collect();
-        let mills_before: HashSet<_> = mills_before.into_iter().collect();
+        let mills_before: HashSet<_> = mills_before.iter().collect();
 
         let count = mills.difference(&mills_before).count();
         self.pound_stones(player.opponent(), count)
@@ -6,4 +6,7 @@ pub mod mill;
 pub mod state;
 pub mod phase;
 pub mod heuristic;
+pub mod score;
+pub mod moves;
+#[allow(clippy::module_inception)]
 pub mod game;
@@ -0,0 +1,58 @@
+use std::fmt::Display;
+
+use super::{coordinate::Coordinate, player::Player, state::State};
+
+/// A move reconstructed from two consecutive states
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Move {
+    /// `None` while placing
+    pub from: Option<Coordinate>,
+    pub to: Coordinate,
+    pub pounded: Vec<Coordinate>,
+}
+
+/// Written as `d2` (placing), `a4-a1` (moving or flying) with `xg7` for every pounded stone
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(from) = self.from {
+            write!(f, "{}-", from.notation())?;
+        }
+        write!(f, "{}", self.to.notation())?;
+        for pounded in self.pounded.iter() {
+            write!(f, "x{}", pounded.notation())?;
+        }
+        Ok(())
+    }
+}
+
+impl State {
+    /// Move of player leading from this state to next
+    pub fn get_move(&self, next: &State, player: Player) -> Move {
+        // Stones of player on before which are gone on after
+        let removed = |before: &State, after: &State, player: Player| -> Vec<Coordinate> {
+            before.get_cells(player.into()).into_iter()
+                .filter(|coordinate| after.get(coordinate) != player.into())
+                .collect()
+        };
+
+        Move {
+            from: removed(self, next, player).into_iter().next(),
+            to: removed(next, self, player).into_iter()
+                .next()
+                .expect("Player did not move"),
+            pounded: removed(self, next, player.opponent()),
+        }
+    }
+
+    /// Moves along a line of play, where player moves first
+    pub fn get_moves(&self, line: &[State], mut player: Player) -> Vec<Move> {
+        let mut state = self;
+        let mut moves = Vec::with_capacity(line.len());
+        for next in line {
+            moves.push(state.get_move(next, player));
+            state = next;
+            player = player.opponent();
+        }
+        moves
+    }
+}
@@ -17,9 +17,9 @@ impl Player {
     }
 }
 
-impl Into<Cell> for Player {
-    fn into(self) -> Cell {
-        match self {
+impl From<Player> for Cell {
+    fn from(player: Player) -> Self {
+        match player {
             Player::White => Cell::White,
             Player::Black => Cell::Black,
         }
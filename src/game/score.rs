@@ -0,0 +1,55 @@
+use std::fmt;
+
+/// Value of winning right at the root, every ply until the end of the game costs one
+pub const WIN: i16 = 10_000;
+/// Bounds of all values returned by the searches
+pub const MIN_VALUE: i16 = -WIN;
+pub const MAX_VALUE: i16 = WIN;
+/// Values beyond this decide the game, heuristic values stay within
+pub const DECISIVE: i16 = WIN - u8::MAX as i16;
+
+/// The searches evaluate nodes at this ply without searching deeper, so that neither the limit nor extensions
+/// and quiescence search can overflow the ply or reach plies, where winning values are no longer decisive
+pub const MAX_PLY: u8 = u8::MAX - 2;
+
+/// Value of winning at the given ply after the root
+pub fn win_in(ply: u8) -> i16 {
+    WIN - ply as i16
+}
+
+pub fn is_decisive(value: i16) -> bool {
+    value.abs() > DECISIVE
+}
+
+/// Make a decisive value relative to the root relative to the node at ply, to store it independent of the path
+pub fn to_node(value: i16, ply: u8) -> i16 {
+    match value {
+        value if value > DECISIVE => value + ply as i16,
+        value if value < -DECISIVE => value - ply as i16,
+        value => value,
+    }
+}
+
+/// Make a decisive value relative to the node at ply relative to the root again
+pub fn to_root(value: i16, ply: u8) -> i16 {
+    match value {
+        value if value > DECISIVE => value - ply as i16,
+        value if value < -DECISIVE => value + ply as i16,
+        value => value,
+    }
+}
+
+/// Value formatted like `+12`, `win in 3` or `loss in 2`, counting the moves of the winner
+#[derive(Debug, Clone, Copy)]
+pub struct Score(pub i16);
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let moves = (WIN - self.0.abs() + 1) / 2;
+        match self.0 {
+            value if value > DECISIVE => write!(f, "win in {}", moves),
+            value if value < -DECISIVE => write!(f, "loss in {}", moves),
+            value => write!(f, "{:+}", value),
+        }
+    }
+}
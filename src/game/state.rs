@@ -2,13 +2,15 @@ use std::{fmt::Display};
 
 use super::{masks::{mask_board, mask_stash, offset_stash}, player::Player, coordinate::Coordinate};
 
+/// Stones of each player in the stash at the start
+pub const STONES: u8 = 9;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct State(pub(super) u64);
 
-impl Into<u64> for &State {
-    fn into(self) -> u64 {
-        self.0
+impl From<&State> for u64 {
+    fn from(state: &State) -> Self {
+        state.0
     }
 }
 
@@ -31,7 +33,7 @@ impl Display for State {
         ");
         for index in 0..24 {
             blueprint = blueprint.replace(
-                (0x41 as u8 + index) as char,
+                (0x41 + index) as char,
                 (&self.get(&Coordinate::from(index))).into()
             );
         }
@@ -44,8 +46,8 @@ impl Display for State {
 impl State {
     pub fn new() -> State {
         let mut state = State(0);
-        state.set_stash(9, Player::White);
-        state.set_stash(9, Player::Black);
+        state.set_stash(STONES, Player::White);
+        state.set_stash(STONES, Player::Black);
         state
     }
 
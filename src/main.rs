@@ -1,36 +1,246 @@
 mod game;
 mod artificial_intelligence;
+mod benchmark;
 
-use artificial_intelligence::{ArtificialIntelligence, alpha_beta_pruning::{AlphaBetaPruning, AlphaBetaPruningOptions}, minimax::{Minimax, MinimaxOptions}};
-use game::{player::Player, state::State};
+use std::{env, fmt::Debug, io::{self, BufRead}, process, thread, time::{Duration, Instant, SystemTime}};
+
+use artificial_intelligence::{ArtificialIntelligence, ArtificialIntelligenceResult, Selection, alpha_beta_pruning::{AlphaBetaPruning, AlphaBetaPruningOptions}, greedy::{Greedy, GreedyOptions}, mcts::{Mcts, MctsOptions}, minimax::{Minimax, MinimaxOptions}, mtdf::{Mtdf, MtdfOptions}, proof_number::{ProofNumberOptions, ProofNumberSearch}, random::{Random, RandomOptions}, skill::{MAX_LEVEL, Skill, SkillOptions}, ponder::{Ponder, Resolution}, search_handle::SearchHandle};
+use game::{heuristic::{HeuristicWeights, TaperedWeights}, player::Player, score::Score, state::State};
+
+const THINKING_TIME: Duration = Duration::from_secs(60);
+const MOVE_TIME: Duration = Duration::from_secs(5);
+const BENCHMARK_LIMIT: u8 = 5;
+const MINIMAX_BENCHMARK_LIMIT: u8 = 4;
+/// Games per level played against the random baseline
+const SKILL_GAMES: u32 = 20;
+/// Games and search depth of the self-play between tapered and single weights
+const TAPERED_GAMES: u32 = 40;
+const TAPERED_LIMIT: u8 = 3;
+/// Maximal number of variations printed per result
+const MULTI_PV_LINES: usize = 5;
 
 fn main() {
-    minimax();
-    alpha_beta_pruning();
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("play") => match args.get(1).map(String::as_str) {
+            Some("minimax") => play(Minimax::new(MinimaxOptions::<HeuristicWeights>::default())),
+            Some("mcts") => play(Mcts::new(MctsOptions {
+                iterations: u32::MAX,
+                time_limit: Some(MOVE_TIME),
+                ..Default::default()
+            })),
+            Some("skill") => play(Skill::new(SkillOptions {
+                level: args.get(2).and_then(|level| level.parse().ok()).unwrap_or(MAX_LEVEL),
+                time_limit: Some(MOVE_TIME),
+                ..Default::default()
+            })),
+            Some("random") => play(Random::new(RandomOptions::default())),
+            Some("greedy") => play(Greedy::new(GreedyOptions {
+                selection: Selection::MillClosing,
+                ..Default::default()
+            })),
+            Some("mtdf") => play(Mtdf::new(MtdfOptions {
+                limit: u8::MAX,
+                time_limit: Some(MOVE_TIME),
+                ..Default::default()
+            })),
+            _ => play(AlphaBetaPruning::new(AlphaBetaPruningOptions {
+                limit: u8::MAX,
+                evaluator: TaperedWeights::default(),
+                time_limit: Some(MOVE_TIME),
+                mill_extension: true,
+                double_mill_extension: true,
+                threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+                selection: Selection::Random(args.get(2).and_then(|seed| seed.parse().ok()).unwrap_or_else(seed)),
+                ..Default::default()
+            })),
+        },
+        Some("bench") => match args.get(1).map(String::as_str) {
+            Some("minimax") => benchmark::minimax_threads(MINIMAX_BENCHMARK_LIMIT),
+            Some("tapered") => benchmark::tapered_weights(&TaperedWeights::default(), args.get(2).and_then(|games| games.parse().ok()).unwrap_or(TAPERED_GAMES), TAPERED_LIMIT),
+            Some("skill") => benchmark::skill_levels(args.get(2).and_then(|games| games.parse().ok()).unwrap_or(SKILL_GAMES)),
+            _ => benchmark::principal_variation_search(BENCHMARK_LIMIT),
+        },
+        Some("regression") => if !benchmark::pruning_regression(BENCHMARK_LIMIT) {
+            process::exit(1);
+        },
+        Some("solve") => solve(&args[1..]),
+        Some("minimax") => minimax(),
+        Some("alpha-beta") => alpha_beta_pruning(),
+        Some("mtdf") => mtdf(),
+        Some("mcts") => mcts(),
+        Some("random") => random(),
+        Some("greedy") => greedy(),
+        _ => {
+            minimax();
+            alpha_beta_pruning();
+            mtdf();
+            mcts();
+            random();
+            greedy();
+        },
+    }
 }
 
 fn minimax() {
-    let options = MinimaxOptions {
+    let options: MinimaxOptions = MinimaxOptions {
         limit: 5,
         ..Default::default()
     };
     let mut ai = Minimax::new(options.clone());
-    println!(
-        "{:?}\n{:?}",
-        options,
-        ai.best_moves(State::new(), Player::White)
-    );
+    println!("{:?}", options);
+    print_result(&State::new(), Player::White, &ai.best_moves(State::new(), Player::White));
 }
 
 fn alpha_beta_pruning() {
-    let options = AlphaBetaPruningOptions {
+    let options: AlphaBetaPruningOptions = AlphaBetaPruningOptions {
+        limit: 5,
+        multi_pv: 3,
+        ..Default::default()
+    };
+    let ai = AlphaBetaPruning::new(options.clone());
+    println!("{:?}", options);
+
+    let start = Instant::now();
+    let handle = SearchHandle::spawn(ai, State::new(), Player::White);
+    let mut depth = None;
+    while !handle.is_finished() {
+        if let Some(progress) = handle.progress() {
+            if depth != Some(progress.depth) {
+                println!("depth {} value {} moves {}", progress.depth, Score(progress.value), progress.states.len());
+                depth = Some(progress.depth);
+            }
+        }
+        if start.elapsed() > THINKING_TIME {
+            handle.stop();
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    let (_, result) = handle.join();
+    print_result(&State::new(), Player::White, &result);
+}
+
+fn mtdf() {
+    let options = MtdfOptions {
         limit: 5,
         ..Default::default()
     };
-    let mut ai = AlphaBetaPruning::new(options.clone());
-    println!(
-        "{:?}\n{:?}",
-        options,
-        ai.best_moves(State::new(), Player::White)
-    );
+    let mut ai = Mtdf::new(options.clone());
+    println!("{:?}", options);
+    print_result(&State::new(), Player::White, &ai.best_moves(State::new(), Player::White));
+}
+
+fn mcts() {
+    let options = MctsOptions::default();
+    let mut ai = Mcts::new(options.clone());
+    println!("{:?}", options);
+    print_result(&State::new(), Player::White, &ai.best_moves(State::new(), Player::White));
+}
+
+fn random() {
+    let options = RandomOptions::default();
+    let mut ai = Random::new(options.clone());
+    println!("{:?}", options);
+    print_result(&State::new(), Player::White, &ai.best_moves(State::new(), Player::White));
+}
+
+fn greedy() {
+    let options = GreedyOptions::default();
+    let mut ai = Greedy::new(options.clone());
+    println!("{:?}", options);
+    print_result(&State::new(), Player::White, &ai.best_moves(State::new(), Player::White));
+}
+
+/// Solve the position after the given moves from the start
+fn solve(moves: &[String]) {
+    let moves: Vec<&str> = moves.iter().map(String::as_str).collect();
+    let (state, player) = benchmark::position(&moves);
+    println!("{}", state);
+    let solution = ProofNumberSearch::new(ProofNumberOptions::default()).solve(state.clone(), player);
+    match &solution.proof_move {
+        Some(next) => println!("{:?} by {}", solution.outcome, state.get_move(next, player)),
+        None => println!("{:?}", solution.outcome),
+    }
+    println!("{:?}", solution.counter);
+}
+
+/// Print the variations in lines like `score +12 pv: d2 a4 d3xg7` or `score win in 2 pv: ...`
+fn print_result<Counter: Debug>(state: &State, player: Player, result: &ArtificialIntelligenceResult<Counter>) {
+    println!("value {} with {} best moves, chosen {}", Score(result.value()), result.states().len(), state.get_move(result.chosen(), player));
+    for variation in result.variations().iter().take(MULTI_PV_LINES) {
+        let moves: Vec<String> = state.get_moves(variation.states(), player).iter()
+            .map(|m| m.to_string())
+            .collect();
+        println!("score {} pv: {}", Score(variation.value()), moves.join(" "));
+    }
+    println!("{:?}", result.counter());
+}
+
+/// Seed of a new game, printed so that the game can be replayed
+fn seed() -> u64 {
+    let seed = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map_or(0, |duration| duration.as_nanos() as u64);
+    println!("seed {}", seed);
+    seed
+}
+
+/// Interactive game of a human (white) against an artificial intelligence, which ponders during the turn of the human
+fn play<AI>(ai: AI)
+where
+    AI: ArtificialIntelligence + Send + 'static,
+    AI::Counter: Debug + Send + 'static,
+{
+    let human = Player::White;
+    let engine = Player::Black;
+
+    let mut state = State::new();
+    let mut ai = Some(ai);
+    let mut ponder: Option<Ponder<AI>> = None;
+    let mut lines = io::stdin().lock().lines();
+
+    loop {
+        println!("{}", state);
+        if state.finished(human) {
+            println!("You lost");
+            break;
+        }
+
+        let next_states = state.next_states(human);
+        state = loop {
+            println!("Your move (like d2, a4-a1 or d3xg7):");
+            let line = match lines.next() {
+                Some(Ok(line)) => line,
+                _ => return,
+            };
+            let chosen = next_states.iter()
+                .find(|next| state.get_move(next, human).to_string() == line.trim());
+            match chosen {
+                Some(next) => break next.clone(),
+                None => println!("Illegal move '{}'", line.trim()),
+            }
+        };
+        if state.finished(engine) {
+            println!("{}\nYou won", state);
+            break;
+        }
+
+        let (engine_ai, result) = match ponder.take() {
+            Some(ponder) => match ponder.resolve(&state) {
+                Resolution::Hit(engine_ai, result) => {
+                    println!("Ponder hit");
+                    (engine_ai, result)
+                },
+                Resolution::Miss(handle) => handle.join(),
+            },
+            None => SearchHandle::spawn(ai.take().expect("Engine is busy"), state.clone(), engine).join(),
+        };
+        print_result(&state, engine, &result);
+        let next = result.chosen().clone();
+        println!("Engine plays {}", state.get_move(&next, engine));
+        state = next;
+
+        if !state.finished(human) {
+            let guess = result.pv().get(1).cloned();
+            ponder = Some(Ponder::start(engine_ai, state.clone(), engine, guess));
+        }
+    }
 }